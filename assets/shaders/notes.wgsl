@@ -0,0 +1,16 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+struct NoteUniforms {
+    // 128 controller values, packed four to a vec4 for uniform alignment
+    cc_values: array<vec4<f32>, 32>,
+};
+
+@group(2) @binding(0) var<uniform> uniforms: NoteUniforms;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let cc = min(u32(mesh.uv.x * 128.0), 127u);
+    let value = uniforms.cc_values[cc / 4u][cc % 4u];
+    let lit = step(1.0 - mesh.uv.y, value);
+    return vec4<f32>(mesh.uv.x * lit, value * lit, 0.3, 1.0);
+}
@@ -0,0 +1,86 @@
+//! Midi utilities without any Bevy dependency, used by `bevy_midi`.
+//!
+//! This crate is `no_std`, so it can be used on embedded targets or in WASM
+//! without pulling in Bevy.
+#![no_std]
+
+mod note;
+mod scale;
+mod sysex;
+
+#[doc(hidden)]
+pub use note::__midi_note;
+pub use note::{MidiNote, NoteOutOfRange};
+pub use scale::{Scale, ScaleDegree};
+pub use sysex::SysExBuffer;
+
+pub const KEY_RANGE: [&str; 12] = [
+    "C", "C#/Db", "D", "D#/Eb", "E", "F", "F#/Gb", "G", "G#/Ab", "A", "A#/Bb", "B",
+];
+
+pub const NOTE_OFF_STATUS: u8 = 0b1000_0000;
+pub const NOTE_ON_STATUS: u8 = 0b1001_0000;
+pub const POLY_AFTERTOUCH_STATUS: u8 = 0b1010_0000;
+pub const CONTROL_CHANGE_STATUS: u8 = 0b1011_0000;
+pub const PROGRAM_CHANGE_STATUS: u8 = 0b1100_0000;
+pub const CHANNEL_PRESSURE_STATUS: u8 = 0b1101_0000;
+pub const PITCH_BEND_STATUS: u8 = 0b1110_0000;
+
+// 2^(n/12) for each semitone in an octave
+const SEMITONE_RATIOS: [f32; 12] = [
+    1.0,
+    1.059_463_1,
+    1.122_462,
+    1.189_207_1,
+    1.259_921,
+    1.334_839_8,
+    core::f32::consts::SQRT_2,
+    1.498_307,
+    1.587_401,
+    1.681_792_8,
+    1.781_797_4,
+    1.887_748_6,
+];
+
+/// Get the name of the pitch class of a midi note, from [`KEY_RANGE`].
+#[must_use]
+pub const fn note_name(key: u8) -> &'static str {
+    KEY_RANGE[key as usize % 12]
+}
+
+/// Get the frequency in Hz of a midi note, in twelve-tone equal temperament
+/// with A4 (note 69) tuned to 440 Hz.
+#[must_use]
+pub fn note_frequency(key: u8) -> f32 {
+    // Frequency of C-1 (note 0), doubled once per octave
+    let mut frequency = 440.0 / SEMITONE_RATIOS[9] / 32.0;
+    for _ in 0..key / 12 {
+        frequency *= 2.0;
+    }
+    frequency * SEMITONE_RATIOS[key as usize % 12]
+}
+
+/// Convert a midi velocity, `0..128`, to `0.0..=1.0`.
+///
+/// ```
+/// # use bevy_midi_core::velocity_to_f32;
+/// assert_eq!(velocity_to_f32(127), 1.0);
+/// assert_eq!(velocity_to_f32(0), 0.0);
+/// ```
+#[must_use]
+pub fn velocity_to_f32(velocity: u8) -> f32 {
+    f32::from(velocity.min(127)) / 127.0
+}
+
+/// Convert `0.0..=1.0` to the nearest midi velocity, clamping values out of range.
+///
+/// ```
+/// # use bevy_midi_core::f32_to_velocity;
+/// assert_eq!(f32_to_velocity(0.5), 64);
+/// assert_eq!(f32_to_velocity(2.0), 127);
+/// ```
+#[must_use]
+pub fn f32_to_velocity(value: f32) -> u8 {
+    // `f32::round` needs std
+    (value.clamp(0.0, 1.0) * 127.0 + 0.5) as u8
+}
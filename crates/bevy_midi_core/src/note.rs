@@ -0,0 +1,186 @@
+use arrayvec::ArrayString;
+use core::fmt::{self, Display, Write};
+
+const SHARP_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+macro_rules! midi_notes {
+    ($($note:ident),* $(,)?) => {
+        /// A midi note, named by pitch class and octave with C4 = 60 (middle C).
+        ///
+        /// Sharps are spelled with an `s`, e.g. [`MidiNote::Cs4`] for C#4, and
+        /// octave -1 as `Neg1`, e.g. [`MidiNote::CNeg1`] for note 0.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u8)]
+        pub enum MidiNote {
+            $($note),*
+        }
+
+        impl MidiNote {
+            /// All 128 notes, indexed by note number.
+            pub const ALL: [MidiNote; 128] = [$(MidiNote::$note),*];
+        }
+    };
+}
+
+// One octave per line
+#[rustfmt::skip]
+midi_notes!(
+    CNeg1, CsNeg1, DNeg1, DsNeg1, ENeg1, FNeg1, FsNeg1, GNeg1, GsNeg1, ANeg1, AsNeg1, BNeg1,
+    C0, Cs0, D0, Ds0, E0, F0, Fs0, G0, Gs0, A0, As0, B0,
+    C1, Cs1, D1, Ds1, E1, F1, Fs1, G1, Gs1, A1, As1, B1,
+    C2, Cs2, D2, Ds2, E2, F2, Fs2, G2, Gs2, A2, As2, B2,
+    C3, Cs3, D3, Ds3, E3, F3, Fs3, G3, Gs3, A3, As3, B3,
+    C4, Cs4, D4, Ds4, E4, F4, Fs4, G4, Gs4, A4, As4, B4,
+    C5, Cs5, D5, Ds5, E5, F5, Fs5, G5, Gs5, A5, As5, B5,
+    C6, Cs6, D6, Ds6, E6, F6, Fs6, G6, Gs6, A6, As6, B6,
+    C7, Cs7, D7, Ds7, E7, F7, Fs7, G7, Gs7, A7, As7, B7,
+    C8, Cs8, D8, Ds8, E8, F8, Fs8, G8, Gs8, A8, As8, B8,
+    C9, Cs9, D9, Ds9, E9, F9, Fs9, G9,
+);
+
+impl MidiNote {
+    /// Get the name of the note's pitch class, from [`KEY_RANGE`](crate::KEY_RANGE).
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        crate::note_name(self as u8)
+    }
+
+    /// Get the note's octave, from -1 to 9, with C4 = 60 (middle C).
+    #[must_use]
+    pub const fn octave(self) -> i8 {
+        (self as u8 / 12) as i8 - 1
+    }
+
+    /// Get the note's name and octave, like its [`Display`] form.
+    ///
+    /// ```
+    /// # use bevy_midi_core::MidiNote;
+    /// assert_eq!(MidiNote::Cs4.full_name().as_str(), "C#4");
+    /// assert_eq!(MidiNote::CNeg1.full_name().as_str(), "C-1");
+    /// ```
+    #[must_use]
+    pub fn full_name(self) -> ArrayString<4> {
+        let mut name = ArrayString::new();
+        // The longest name, like "C#-1", fits
+        let _ = write!(name, "{}", self);
+        name
+    }
+
+    /// Parse a note name like `"C4"`, `"C#4"`, `"Db4"` or `"G-1"`.
+    ///
+    /// Returns `None` for invalid names and notes outside the midi range.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<MidiNote> {
+        let mut chars = name.chars();
+        let pitch_class: i16 = match chars.next()?.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let rest = chars.as_str();
+        let (accidental, octave) = match rest.as_bytes().first()? {
+            b'#' => (1, &rest[1..]),
+            b'b' => (-1, &rest[1..]),
+            _ => (0, rest),
+        };
+        let octave: i16 = octave.parse().ok()?;
+        let key = (octave + 1) * 12 + pitch_class + accidental;
+        u8::try_from(key)
+            .ok()
+            .and_then(|key| MidiNote::ALL.get(usize::from(key)).copied())
+    }
+}
+
+/// Converts a note number to a note; numbers above 127 are an error.
+///
+/// ```
+/// # use bevy_midi_core::{MidiNote, NoteOutOfRange};
+/// assert_eq!(MidiNote::try_from(60), Ok(MidiNote::C4));
+/// assert_eq!(MidiNote::try_from(200), Err(NoteOutOfRange(200)));
+/// ```
+impl TryFrom<u8> for MidiNote {
+    type Error = NoteOutOfRange;
+
+    fn try_from(key: u8) -> Result<Self, Self::Error> {
+        MidiNote::ALL
+            .get(usize::from(key))
+            .copied()
+            .ok_or(NoteOutOfRange(key))
+    }
+}
+
+/// The error for converting a note number above 127 to a [`MidiNote`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NoteOutOfRange(pub u8);
+
+impl Display for NoteOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "midi note out of range: {}", self.0)
+    }
+}
+
+impl core::error::Error for NoteOutOfRange {}
+
+impl From<MidiNote> for u8 {
+    fn from(note: MidiNote) -> Self {
+        note as u8
+    }
+}
+
+/// Formats the note like `C#4`.
+impl Display for MidiNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = *self as u8;
+        write!(f, "{}{}", SHARP_NAMES[usize::from(key % 12)], self.octave())
+    }
+}
+
+/// Get the number of a note from its name, as a `const` expression.
+///
+/// Sharps are spelled with an `s` and flats with a `b`, like in [`MidiNote`];
+/// octaves range from -1 to 9, with C4 = 60 (middle C). Invalid names and notes
+/// outside the midi range fail to compile.
+///
+/// ```
+/// # use bevy_midi_core::midi_note;
+/// assert_eq!(midi_note!(C 4), 60);
+/// assert_eq!(midi_note!(Fs 3), 54);
+/// assert_eq!(midi_note!(Gb 3), 54);
+/// assert_eq!(midi_note!(C -1), 0);
+/// ```
+#[macro_export]
+macro_rules! midi_note {
+    ($name:ident $octave:literal) => {{
+        const KEY: u8 = $crate::__midi_note(stringify!($name), $octave);
+        KEY
+    }};
+}
+
+#[doc(hidden)]
+pub const fn __midi_note(name: &str, octave: i8) -> u8 {
+    let pitch_class: i16 = match name.as_bytes() {
+        b"C" => 0,
+        b"Cs" | b"Db" => 1,
+        b"D" => 2,
+        b"Ds" | b"Eb" => 3,
+        b"E" => 4,
+        b"F" => 5,
+        b"Fs" | b"Gb" => 6,
+        b"G" => 7,
+        b"Gs" | b"Ab" => 8,
+        b"A" => 9,
+        b"As" | b"Bb" => 10,
+        b"B" => 11,
+        _ => panic!("invalid note name"),
+    };
+    let key = (octave as i16 + 1) * 12 + pitch_class;
+    assert!(key >= 0 && key < 128, "note outside the midi range");
+    key as u8
+}
@@ -0,0 +1,163 @@
+/// A musical scale, as the semitone offsets of its degrees from the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Scale {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    Chromatic,
+}
+
+impl Scale {
+    /// All built-in scales.
+    pub const ALL: [Scale; 13] = [
+        Scale::Major,
+        Scale::NaturalMinor,
+        Scale::HarmonicMinor,
+        Scale::MelodicMinor,
+        Scale::Dorian,
+        Scale::Phrygian,
+        Scale::Lydian,
+        Scale::Mixolydian,
+        Scale::Locrian,
+        Scale::MajorPentatonic,
+        Scale::MinorPentatonic,
+        Scale::Blues,
+        Scale::Chromatic,
+    ];
+
+    /// Get the semitone offset of each degree of the scale from its root,
+    /// within one octave.
+    #[must_use]
+    pub const fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            // Ascending form
+            Scale::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        }
+    }
+
+    /// Get the number of degrees in one octave of the scale.
+    #[must_use]
+    pub const fn degree_count(self) -> usize {
+        self.intervals().len()
+    }
+}
+
+/// Conversion from scale degrees to midi notes, for picking notes relative to
+/// a key rather than by absolute pitch.
+pub struct ScaleDegree;
+
+impl ScaleDegree {
+    /// Get the midi note of the 0-based `degree` of `scale`, starting on the
+    /// note `root` and transposed by `octave` octaves.
+    ///
+    /// Degrees past the last one of the scale continue into the next octaves.
+    /// Returns `None` if `root` or the note is outside of `0..128`.
+    ///
+    /// ```
+    /// # use bevy_midi_core::{Scale, ScaleDegree};
+    /// // C major, from middle C
+    /// assert_eq!(ScaleDegree::to_midi_note(60, Scale::Major, 2, 0), Some(64));
+    /// assert_eq!(ScaleDegree::to_midi_note(60, Scale::Major, 7, 0), Some(72));
+    /// assert_eq!(ScaleDegree::to_midi_note(60, Scale::Major, 0, -1), Some(48));
+    /// assert_eq!(ScaleDegree::to_midi_note(60, Scale::Major, 0, 6), None);
+    /// assert_eq!(ScaleDegree::to_midi_note(200, Scale::Major, 0, -10), None);
+    ///
+    /// // A full octave of every scale ends on the root, one octave up
+    /// for scale in Scale::ALL {
+    ///     let top = scale.degree_count() as u8;
+    ///     assert_eq!(ScaleDegree::to_midi_note(57, scale, top, 0), Some(69));
+    ///     for degree in 1..top {
+    ///         let note = ScaleDegree::to_midi_note(57, scale, degree, 0).unwrap();
+    ///         let previous = ScaleDegree::to_midi_note(57, scale, degree - 1, 0).unwrap();
+    ///         assert!(previous < note && note < 69);
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn to_midi_note(root: u8, scale: Scale, degree: u8, octave: i8) -> Option<u8> {
+        if root >= 128 {
+            return None;
+        }
+        let intervals = scale.intervals();
+        let degree = usize::from(degree);
+        let octave = i16::from(octave) + (degree / intervals.len()) as i16;
+        let note = i16::from(root) + octave * 12 + i16::from(intervals[degree % intervals.len()]);
+        u8::try_from(note).ok().filter(|note| *note < 128)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `expected` is one octave of `scale` from middle C, including the closing C
+    fn assert_notes(scale: Scale, expected: &[u8]) {
+        assert_eq!(expected.len(), scale.degree_count() + 1, "{:?}", scale);
+        for (degree, expected) in (0u8..).zip(expected) {
+            let note = ScaleDegree::to_midi_note(60, scale, degree, 0);
+            assert_eq!(note, Some(*expected), "{:?} degree {}", scale, degree);
+        }
+    }
+
+    #[test]
+    fn built_in_scales() {
+        // C D E F G A B C
+        assert_notes(Scale::Major, &[60, 62, 64, 65, 67, 69, 71, 72]);
+        // C D Eb F G Ab Bb C
+        assert_notes(Scale::NaturalMinor, &[60, 62, 63, 65, 67, 68, 70, 72]);
+        // C D Eb F G Ab B C
+        assert_notes(Scale::HarmonicMinor, &[60, 62, 63, 65, 67, 68, 71, 72]);
+        // C D Eb F G A B C
+        assert_notes(Scale::MelodicMinor, &[60, 62, 63, 65, 67, 69, 71, 72]);
+        // C D Eb F G A Bb C
+        assert_notes(Scale::Dorian, &[60, 62, 63, 65, 67, 69, 70, 72]);
+        // C Db Eb F G Ab Bb C
+        assert_notes(Scale::Phrygian, &[60, 61, 63, 65, 67, 68, 70, 72]);
+        // C D E F# G A B C
+        assert_notes(Scale::Lydian, &[60, 62, 64, 66, 67, 69, 71, 72]);
+        // C D E F G A Bb C
+        assert_notes(Scale::Mixolydian, &[60, 62, 64, 65, 67, 69, 70, 72]);
+        // C Db Eb F Gb Ab Bb C
+        assert_notes(Scale::Locrian, &[60, 61, 63, 65, 66, 68, 70, 72]);
+        // C D E G A C
+        assert_notes(Scale::MajorPentatonic, &[60, 62, 64, 67, 69, 72]);
+        // C Eb F G Bb C
+        assert_notes(Scale::MinorPentatonic, &[60, 63, 65, 67, 70, 72]);
+        // C Eb F F# G Bb C
+        assert_notes(Scale::Blues, &[60, 63, 65, 66, 67, 70, 72]);
+        assert_notes(
+            Scale::Chromatic,
+            &[60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72],
+        );
+    }
+
+    #[test]
+    fn invalid_root() {
+        assert_eq!(ScaleDegree::to_midi_note(128, Scale::Major, 0, -1), None);
+        assert_eq!(
+            ScaleDegree::to_midi_note(255, Scale::Chromatic, 0, -10),
+            None
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use arrayvec::ArrayVec;
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+/// A fixed-capacity buffer accumulating a System Exclusive message that arrives
+/// in several chunks, without allocating.
+///
+/// Bytes past the capacity (or a lower limit set with [`SysExBuffer::with_limit`])
+/// are discarded, and the message is marked as overflowed.
+#[derive(Clone, Debug)]
+pub struct SysExBuffer<const N: usize> {
+    data: ArrayVec<u8, N>,
+    limit: usize,
+    overflowed: bool,
+    // Tracked apart from `data`, which stays empty with a limit of 0
+    started: bool,
+    complete: bool,
+}
+
+impl<const N: usize> Default for SysExBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> SysExBuffer<N> {
+    /// Create an empty buffer holding up to `N` bytes.
+    #[must_use]
+    pub const fn new() -> Self {
+        SysExBuffer {
+            data: ArrayVec::new_const(),
+            limit: N,
+            overflowed: false,
+            started: false,
+            complete: false,
+        }
+    }
+
+    /// Create an empty buffer holding up to `limit` bytes, capped to `N`.
+    #[must_use]
+    pub const fn with_limit(limit: usize) -> Self {
+        SysExBuffer {
+            data: ArrayVec::new_const(),
+            limit: if limit < N { limit } else { N },
+            overflowed: false,
+            started: false,
+            complete: false,
+        }
+    }
+
+    /// Check whether a message has been started but not completed yet.
+    #[must_use]
+    pub fn is_accumulating(&self) -> bool {
+        self.started && !self.complete
+    }
+
+    /// Check whether the message's `0xF7` end marker has arrived.
+    ///
+    /// An overflowed message is complete once its `0xF7` arrives, even though
+    /// the end marker itself may not have fit.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Check whether bytes had to be discarded since the buffer was last cleared.
+    #[must_use]
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Get the buffered bytes.
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Empty the buffer.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.overflowed = false;
+        self.started = false;
+        self.complete = false;
+    }
+
+    /// Append a chunk of a message. A chunk starting with `0xF0`, or any chunk
+    /// after a complete message, starts a new message.
+    ///
+    /// Returns `true` once the message is complete.
+    pub fn push(&mut self, chunk: &[u8]) -> bool {
+        if chunk.first() == Some(&SYSEX_START) || self.complete {
+            self.clear();
+        }
+        self.started = true;
+        for byte in chunk {
+            if *byte == SYSEX_END {
+                self.complete = true;
+            }
+            if self.data.len() < self.limit {
+                self.data.push(*byte);
+            } else {
+                self.overflowed = true;
+                // Keep the end marker so completion can still be detected
+                if *byte == SYSEX_END {
+                    if let Some(last) = self.data.last_mut() {
+                        *last = SYSEX_END;
+                    }
+                }
+            }
+        }
+        self.is_complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_chunk() {
+        let mut buffer = SysExBuffer::<16>::new();
+        assert!(buffer.push(&[0xF0, 0x7E, 0x01, 0xF7]));
+        assert_eq!(buffer.as_slice(), &[0xF0, 0x7E, 0x01, 0xF7]);
+        assert!(!buffer.is_overflowed());
+        assert!(!buffer.is_accumulating());
+    }
+
+    #[test]
+    fn several_chunks() {
+        let mut buffer = SysExBuffer::<16>::new();
+        assert!(!buffer.push(&[0xF0, 0x7E]));
+        assert!(buffer.is_accumulating());
+        assert!(!buffer.push(&[0x01, 0x02]));
+        assert!(buffer.push(&[0x03, 0xF7]));
+        assert_eq!(buffer.as_slice(), &[0xF0, 0x7E, 0x01, 0x02, 0x03, 0xF7]);
+    }
+
+    #[test]
+    fn overflow_keeps_the_end_marker() {
+        let mut buffer = SysExBuffer::<16>::with_limit(3);
+        assert!(!buffer.push(&[0xF0, 0x01, 0x02, 0x03]));
+        assert!(buffer.is_overflowed());
+        assert!(buffer.push(&[0x04, 0xF7]));
+        assert_eq!(buffer.as_slice(), &[0xF0, 0x01, 0xF7]);
+    }
+
+    #[test]
+    fn zero_limit_still_tracks_the_message() {
+        let mut buffer = SysExBuffer::<16>::with_limit(0);
+        assert!(!buffer.push(&[0xF0, 0x01]));
+        assert!(buffer.is_accumulating());
+        assert!(buffer.push(&[0x02, 0xF7]));
+        assert!(buffer.is_overflowed());
+        assert!(buffer.as_slice().is_empty());
+    }
+
+    #[test]
+    fn start_byte_restarts_the_message() {
+        let mut buffer = SysExBuffer::<16>::new();
+        buffer.push(&[0xF0, 0x01]);
+        assert!(buffer.push(&[0xF0, 0x02, 0xF7]));
+        assert_eq!(buffer.as_slice(), &[0xF0, 0x02, 0xF7]);
+    }
+
+    #[test]
+    fn clear_resets_the_state() {
+        let mut buffer = SysExBuffer::<16>::with_limit(1);
+        buffer.push(&[0xF0, 0x01]);
+        buffer.clear();
+        assert!(!buffer.is_accumulating());
+        assert!(!buffer.is_overflowed());
+        assert!(!buffer.is_complete());
+    }
+}
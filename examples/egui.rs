@@ -195,11 +195,9 @@ impl PianoRoll {
             .enumerate()
             .for_each(|(index, (prev, next))| {
                 if prev != next {
-                    println!(
-                        "Pressed {}{}",
-                        KEY_RANGE[index % 12],
-                        (self.bottom_note_index + index) / 12
-                    );
+                    if let Some(note) = note_at(self.bottom_note_index + index) {
+                        println!("Pressed {}", note.full_name());
+                    }
                     /*
                     if *next {
 
@@ -278,7 +276,9 @@ impl PianoRoll {
                         ImageButton::new(SizedTexture::new(texture_id, TOP_KEY_SIZE)).tint(color);
                     if ui.add(button_top).clicked() {
                         //sync.trigger_note(index, selected_instrument);
-                        println!("Pressed {}{}", KEY_RANGE[index % 12], index / 12);
+                        if let Some(note) = note_at(index) {
+                            println!("Pressed {}", note.full_name());
+                        }
                     };
                 });
             });
@@ -300,7 +300,9 @@ impl PianoRoll {
 
                         if ui.add(button_bottom).clicked() {
                             //sync.trigger_note(index, selected_instrument);
-                            println!("Pressed {}{}", KEY_RANGE[index % 12], index / 12);
+                            if let Some(note) = note_at(index) {
+                                println!("Pressed {}", note.full_name());
+                            }
                         };
                     }
                 }
@@ -309,6 +311,13 @@ impl PianoRoll {
     }
 }
 
+// The note of a key of the piano, if it's in the midi range
+fn note_at(index: usize) -> Option<MidiNote> {
+    u8::try_from(index)
+        .ok()
+        .and_then(|key| MidiNote::try_from(key).ok())
+}
+
 fn ui_example(egui_context: Query<&EguiContext>, mut piano: ResMut<PianoRoll>) {
     if let Ok(ctx) = egui_context.get_single() {
         egui::Window::new("Virtual Keyboard Piano").show(ctx.get(), |ui| {
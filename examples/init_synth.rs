@@ -0,0 +1,140 @@
+//! Initializes a hardware synth: asks the device to identify itself, then sends
+//! the SysEx messages of a `.syx` patch file.
+//!
+//! Usage: `cargo run --example init_synth -- <port name> [patch.syx]`
+//!
+//! The synth must appear both as an input and as an output port whose names
+//! contain `<port name>`. The default patch, `assets/sysex/init_patch.syx`,
+//! only holds universal messages (GM System On and master volume), so replace
+//! it with a dump of your own patch.
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use bevy_midi::prelude::*;
+use std::time::Duration;
+
+const DEFAULT_PATCH: &str = "assets/sysex/init_patch.syx";
+const TIMEOUT_SECS: f32 = 5.0;
+
+#[derive(Resource)]
+struct Patch {
+    port_name: String,
+    messages: Vec<Vec<u8>>,
+    identity_requested: bool,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(port_name) = args.next() else {
+        eprintln!("Usage: init_synth <port name> [patch.syx]");
+        return;
+    };
+    let path = args.next().unwrap_or_else(|| DEFAULT_PATCH.to_string());
+    let bytes = std::fs::read(&path).expect("Couldn't read patch file");
+    let messages = split_sysex(&bytes);
+
+    App::new()
+        .add_plugins(
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            ))),
+        )
+        .add_plugins(LogPlugin::default())
+        .add_plugins((MidiInputPlugin, MidiOutputPlugin))
+        .insert_resource(Patch {
+            port_name,
+            messages,
+            identity_requested: false,
+        })
+        .add_systems(
+            Update,
+            (connect, request_identity, send_patch, timeout).chain(),
+        )
+        .run();
+}
+
+/// Split the contents of a `.syx` file into its `F0 .. F7` messages.
+fn split_sysex(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut start = None;
+    for (i, byte) in bytes.iter().enumerate() {
+        match (byte, start) {
+            (0xF0, _) => start = Some(i),
+            (0xF7, Some(s)) => {
+                messages.push(bytes[s..=i].to_vec());
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    messages
+}
+
+fn connect(
+    patch: Res<Patch>,
+    input: Res<MidiInput>,
+    output: Res<MidiOutput>,
+    input_connection: Res<MidiInputConnection>,
+    output_connection: Res<MidiOutputConnection>,
+) {
+    let ports_changed = input.is_changed() || output.is_changed();
+    if !ports_changed || input_connection.is_connected() || output_connection.is_connected() {
+        return;
+    }
+    if connect_matching_ports(&patch.port_name, &input, &output).is_some() {
+        info!("Connecting to {}", patch.port_name);
+    }
+}
+
+fn request_identity(
+    mut patch: ResMut<Patch>,
+    output: Res<MidiOutput>,
+    input_connection: Res<MidiInputConnection>,
+    output_connection: Res<MidiOutputConnection>,
+) {
+    if patch.identity_requested
+        || !input_connection.is_connected()
+        || !output_connection.is_connected()
+    {
+        return;
+    }
+    info!("Sending identity request");
+    output.send_identity_request();
+    patch.identity_requested = true;
+}
+
+fn send_patch(
+    patch: Res<Patch>,
+    output: Res<MidiOutput>,
+    mut sysex: EventReader<MidiSysExData>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for data in sysex.read() {
+        let Some(identity) = data.try_as_identity_response() else {
+            continue;
+        };
+        info!(
+            "Found device: manufacturer {:#X}, family {:#X}, member {:#X}, revision {:?}",
+            identity.manufacturer_id, identity.family_code, identity.member_code, identity.revision
+        );
+        for message in &patch.messages {
+            output.send_sysex(message.clone());
+        }
+        info!("Sent {} SysEx messages", patch.messages.len());
+        exit.send(AppExit::Success);
+        return;
+    }
+}
+
+fn timeout(time: Res<Time>, patch: Res<Patch>, mut exit: EventWriter<AppExit>) {
+    if time.elapsed_seconds() > TIMEOUT_SECS {
+        if patch.identity_requested {
+            error!("The device didn't answer the identity request");
+        } else {
+            error!(
+                "Couldn't find input and output ports matching {}",
+                patch.port_name
+            );
+        }
+        exit.send(AppExit::error());
+    }
+}
@@ -21,8 +21,8 @@ const KEY_PORT_MAP: [(KeyCode, usize); 10] = [
 fn main() {
     App::new()
         .insert_resource(MidiInputSettings {
-            port_name: "input",
-            client_name: "input",
+            port_name: "input".to_string(),
+            client_name: "input".to_string(),
             ..default()
         })
         .add_plugins(DefaultPlugins.set(LogPlugin {
@@ -74,12 +74,11 @@ pub struct Instructions;
 fn show_ports(input: Res<MidiInput>, mut instructions: Query<&mut Text, With<Instructions>>) {
     if input.is_changed() {
         let text_section = &mut instructions.single_mut().sections[1];
-        text_section.value = "Available input ports:\n\n".to_string();
-        for (i, (name, _)) in input.ports().iter().enumerate() {
-            text_section
-                .value
-                .push_str(format!("Port {:?}: {:?}\n", i, name).as_str());
-        }
+        text_section.value = if input.ports_ready() {
+            format!("Available input ports:\n\n{}", input.ports_display())
+        } else {
+            "Scanning for input ports...".to_string()
+        };
     }
 }
 
@@ -0,0 +1,90 @@
+//! Measures the round-trip latency of midi messages through a virtual loopback port,
+//! without needing any midi hardware.
+//!
+//! Run with `cargo run --release --example latency_test --features diagnostics`.
+//! Virtual ports are only available on unix platforms.
+
+#[cfg(unix)]
+fn main() {
+    latency_test::main();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!(
+        "The latency_test example requires virtual midi ports, which are only available on unix"
+    );
+}
+
+#[cfg(unix)]
+mod latency_test {
+    use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+    use bevy_midi::prelude::*;
+    use midir::os::unix::{VirtualInput, VirtualOutput};
+    use std::time::Duration;
+
+    const LOOPBACK_IN: &str = "bevy_midi latency in";
+    const LOOPBACK_OUT: &str = "bevy_midi latency out";
+    const DURATION_SECS: f32 = 10.0;
+
+    pub fn main() {
+        // Forward everything received on LOOPBACK_IN to LOOPBACK_OUT
+        let out = midir::MidiOutput::new("latency_test")
+            .expect("Couldn't create midi output")
+            .create_virtual(LOOPBACK_OUT)
+            .expect("Couldn't create virtual output port");
+        let _loopback = midir::MidiInput::new("latency_test")
+            .expect("Couldn't create midi input")
+            .create_virtual(
+                LOOPBACK_IN,
+                |_, message, out: &mut midir::MidiOutputConnection| {
+                    let _ = out.send(message);
+                },
+                out,
+            )
+            .expect("Couldn't create virtual input port");
+
+        App::new()
+            .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+                Duration::from_secs_f64(1.0 / 60.0),
+            )))
+            .add_plugins(LogPlugin::default())
+            .add_plugins((MidiInputPlugin, MidiOutputPlugin, MidiLatencyProbePlugin))
+            .add_systems(Update, (connect, report))
+            .run();
+    }
+
+    fn connect(
+        input: Res<MidiInput>,
+        output: Res<MidiOutput>,
+        input_connection: Res<MidiInputConnection>,
+        output_connection: Res<MidiOutputConnection>,
+    ) {
+        if input.is_changed() && !input_connection.is_connected() {
+            input.connect_by_name(LOOPBACK_OUT);
+        }
+        if output.is_changed() && !output_connection.is_connected() {
+            output.connect_by_name(LOOPBACK_IN);
+        }
+    }
+
+    fn report(
+        time: Res<Time>,
+        diagnostic: Res<MidiLatencyDiagnostic>,
+        mut exit: EventWriter<AppExit>,
+    ) {
+        if diagnostic.is_changed() && diagnostic.samples > 0 {
+            info!(
+                "Latency: min {} us, max {} us, avg {} us over {} probes ({} lost)",
+                diagnostic.min_us,
+                diagnostic.max_us,
+                diagnostic.avg_us,
+                diagnostic.samples,
+                diagnostic.lost
+            );
+        }
+        if time.elapsed_seconds() > DURATION_SECS {
+            exit.send(AppExit::Success);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Sends notes through a virtual midi port and checks that [`MidiInput`]
+//! receives them, without needing any midi hardware.
+//!
+//! Virtual ports are only available on unix platforms.
+
+#[cfg(unix)]
+fn main() {
+    loopback::main();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("The loopback example requires virtual midi ports, which are only available on unix");
+}
+
+#[cfg(unix)]
+mod loopback {
+    use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+    use bevy_midi::prelude::*;
+    use midir::os::unix::VirtualOutput;
+    use std::time::Duration;
+
+    const VIRTUAL_PORT: &str = "bevy_midi loopback";
+    const TEST_NOTES: [u8; 8] = [
+        midi_note!(C 4),
+        midi_note!(D 4),
+        midi_note!(E 4),
+        midi_note!(F 4),
+        midi_note!(G 4),
+        midi_note!(A 4),
+        midi_note!(B 4),
+        midi_note!(C 5),
+    ];
+    const TIMEOUT_SECS: f32 = 5.0;
+
+    struct Loopback {
+        connection: midir::MidiOutputConnection,
+        sent: usize,
+        received: usize,
+    }
+
+    pub fn main() {
+        let connection = midir::MidiOutput::new("loopback")
+            .expect("Couldn't create midi output")
+            .create_virtual(VIRTUAL_PORT)
+            .expect("Couldn't create virtual output port");
+
+        App::new()
+            .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+                Duration::from_secs_f64(1.0 / 60.0),
+            )))
+            .add_plugins(LogPlugin::default())
+            .add_plugins(MidiInputPlugin)
+            .insert_non_send_resource(Loopback {
+                connection,
+                sent: 0,
+                received: 0,
+            })
+            .add_systems(Update, (connect, send_notes, check_received, timeout))
+            .run();
+    }
+
+    fn connect(input: Res<MidiInput>, connection: Res<MidiInputConnection>) {
+        if input.is_changed() && !connection.is_connected() {
+            if let Some((_, port)) = input
+                .ports()
+                .iter()
+                .find(|(name, _)| name.contains(VIRTUAL_PORT))
+            {
+                info!("Connecting to {}", port.name());
+                input.connect(port.clone());
+            }
+        }
+    }
+
+    fn send_notes(connection: Res<MidiInputConnection>, mut loopback: NonSendMut<Loopback>) {
+        if !connection.is_connected() || loopback.sent == TEST_NOTES.len() {
+            return;
+        }
+        let note = TEST_NOTES[loopback.sent];
+        loopback
+            .connection
+            .send(&[0b1001_0000, note, 127])
+            .expect("Couldn't send note");
+        loopback.sent += 1;
+    }
+
+    fn check_received(
+        mut midi_data: EventReader<MidiData>,
+        mut loopback: NonSendMut<Loopback>,
+        mut exit: EventWriter<AppExit>,
+    ) {
+        for data in midi_data.read() {
+            let expected = TEST_NOTES[loopback.received];
+            let [_, note, _] = data.message.msg;
+            if !data.message.is_note_on() || note != expected {
+                error!(
+                    "Expected NoteOn {}, received {:?}",
+                    expected, data.message.msg
+                );
+                exit.send(AppExit::error());
+                return;
+            }
+            info!("Received NoteOn {}", note);
+            loopback.received += 1;
+
+            if loopback.received == TEST_NOTES.len() {
+                info!("All {} notes received in order", TEST_NOTES.len());
+                exit.send(AppExit::Success);
+                return;
+            }
+        }
+    }
+
+    fn timeout(time: Res<Time>, loopback: NonSend<Loopback>, mut exit: EventWriter<AppExit>) {
+        if time.elapsed_seconds() > TIMEOUT_SECS {
+            error!(
+                "Timed out after receiving {} of {} notes",
+                loopback.received,
+                TEST_NOTES.len()
+            );
+            exit.send(AppExit::error());
+        }
+    }
+}
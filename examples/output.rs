@@ -17,21 +17,22 @@ const KEY_PORT_MAP: [(KeyCode, usize); 10] = [
     (KeyCode::Digit9, 9),
 ];
 
-const KEY_NOTE_MAP: [(KeyCode, u8); 7] = [
-    (KeyCode::KeyA, 57),
-    (KeyCode::KeyB, 59),
-    (KeyCode::KeyC, 60),
-    (KeyCode::KeyD, 62),
-    (KeyCode::KeyE, 64),
-    (KeyCode::KeyF, 65),
-    (KeyCode::KeyG, 67),
+const KEY_NOTE_MAP: [(KeyCode, MidiNote); 7] = [
+    (KeyCode::KeyA, MidiNote::A3),
+    (KeyCode::KeyB, MidiNote::B3),
+    (KeyCode::KeyC, MidiNote::C4),
+    (KeyCode::KeyD, MidiNote::D4),
+    (KeyCode::KeyE, MidiNote::E4),
+    (KeyCode::KeyF, MidiNote::F4),
+    (KeyCode::KeyG, MidiNote::G4),
 ];
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .insert_resource(MidiOutputSettings {
-            port_name: "output",
+            port_name: "output".to_string(),
+            ..default()
         })
         .add_plugins(MidiOutputPlugin)
         .add_systems(
@@ -74,10 +75,10 @@ fn disconnect(input: Res<ButtonInput<KeyCode>>, output: Res<MidiOutput>) {
 fn play_notes(input: Res<ButtonInput<KeyCode>>, output: Res<MidiOutput>) {
     for (keycode, note) in &KEY_NOTE_MAP {
         if input.just_pressed(*keycode) {
-            output.send([0b1001_0000, *note, 127].into()); // Note on, channel 1, max velocity
+            output.note_on(0, (*note).into(), 127);
         }
         if input.just_released(*keycode) {
-            output.send([0b1000_0000, *note, 127].into()); // Note on, channel 1, max velocity
+            output.note_off(0, (*note).into(), 127);
         }
     }
 }
@@ -88,12 +89,7 @@ pub struct Instructions;
 fn show_ports(output: Res<MidiOutput>, mut instructions: Query<&mut Text, With<Instructions>>) {
     if output.is_changed() {
         let text_section = &mut instructions.single_mut().sections[1];
-        text_section.value = "Available output ports:\n\n".to_string();
-        for (i, (name, _)) in output.ports().iter().enumerate() {
-            text_section
-                .value
-                .push_str(format!("Port {:?}: {:?}\n", i, name).as_str());
-        }
+        text_section.value = format!("Available output ports:\n\n{}", output.ports_display());
     }
 }
 
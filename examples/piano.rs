@@ -68,14 +68,17 @@ fn setup(
         },
     ));
 
+    // Press F12 to release stuck notes
+    cmds.spawn(MidiPanicButton::default());
+
     let pos: Vec3 = Vec3::new(0., 0., 0.);
 
     let mut black_key: Handle<Mesh> = asset_server.load("models/black_key.gltf#Mesh0/Primitive0");
     let mut white_key_0: Handle<Mesh> = asset_server.load("models/white_key_0.gltf#Mesh0/Primitive0");
     let mut white_key_1: Handle<Mesh> = asset_server.load("models/white_key_1.gltf#Mesh0/Primitive0");
     let mut white_key_2: Handle<Mesh> = asset_server.load("models/white_key_2.gltf#Mesh0/Primitive0");
-    let b_mat = materials.add(Color::rgb(0.1, 0.1, 0.1));
-    let w_mat = materials.add(Color::rgb(1.0, 1.0, 1.0));
+    let b_mat = materials.add(Color::srgb(0.1, 0.1, 0.1));
+    let w_mat = materials.add(Color::srgb(1.0, 1.0, 1.0));
 
     //Create keyboard layout
     let pos_black = pos + Vec3::new(0., 0.06, 0.);
@@ -117,7 +120,8 @@ fn spawn_note(
             ..Default::default()
         },
         Key {
-            key_val: format!("{}{}", key, oct),
+            // Octaves are numbered like MidiNote::octave, from -1
+            key_val: format!("{}{}", key, oct - 1),
             y_reset: pos.y,
         },
         PickableBundle::default(),
@@ -149,30 +153,30 @@ fn handle_midi_input(
 ) {
     for data in midi_events.read() {
         let [_, index, _value] = data.message.msg;
-        let off = index % 12;
-        let oct = index.overflowing_div(12).0;
-        let key_str = KEY_RANGE.iter().nth(off.into()).unwrap();
+        let Ok(note) = MidiNote::try_from(index) else {
+            continue;
+        };
+        let key_val = format!("{}{}", note.name(), note.octave());
 
         if data.message.is_note_on() {
             for (entity, key) in query.iter() {
-                if key.key_val.eq(&format!("{}{}", key_str, oct).to_string()) {
+                if key.key_val == key_val {
                     commands.entity(entity).insert(PressedKey);
                 }
             }
         } else if data.message.is_note_off() {
             for (entity, key) in query.iter() {
-                if key.key_val.eq(&format!("{}{}", key_str, oct).to_string()) {
+                if key.key_val == key_val {
                     commands.entity(entity).remove::<PressedKey>();
                 }
             }
-        } else {
         }
     }
 }
 
 fn connect_to_first_input_port(input: Res<MidiInput>) {
     if input.is_changed() {
-        if let Some((_, port)) = input.ports().get(0) {
+        if let Some((_, port)) = input.ports().first() {
             input.connect(port.clone());
         }
     }
@@ -180,7 +184,7 @@ fn connect_to_first_input_port(input: Res<MidiInput>) {
 
 fn connect_to_first_output_port(input: Res<MidiOutput>) {
     if input.is_changed() {
-        if let Some((_, port)) = input.ports().get(0) {
+        if let Some((_, port)) = input.ports().first() {
             input.connect(port.clone());
         }
     }
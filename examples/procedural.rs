@@ -0,0 +1,164 @@
+//! Generates an evolving midi sequence from entities, timed by an incoming midi clock.
+//!
+//! Every entity with a [`Pitch`], [`Rhythm`] and [`Velocity`] plays its note at
+//! its own rate. Spawning and despawning entities adds and removes voices.
+//!
+//! The first input port must send midi clock, e.g. from a DAW or drum machine,
+//! and notes are sent to the first output port.
+
+use bevy::prelude::*;
+use bevy_midi::prelude::*;
+
+const ROOT: u8 = midi_note!(C 4);
+const SCALE: Scale = Scale::MinorPentatonic;
+const CHANNEL: u8 = 0;
+
+/// The note an entity plays.
+#[derive(Component)]
+struct Pitch(u8);
+
+/// How many clock ticks there are between the entity's notes.
+#[derive(Component)]
+struct Rhythm(u64);
+
+#[derive(Component)]
+struct Velocity(u8);
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins((
+            MidiInputPlugin,
+            MidiOutputPlugin,
+            MidiClockPlugin::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                connect_to_first_ports,
+                (spawn_voice, despawn_voice, play_voices).chain(),
+                show_status,
+            ),
+        )
+        .run();
+}
+
+fn connect_to_first_ports(input: Res<MidiInput>, output: Res<MidiOutput>) {
+    if input.is_changed() {
+        if let Some((_, port)) = input.ports().first() {
+            input.connect(port.clone());
+        }
+    }
+    if output.is_changed() {
+        if let Some((_, port)) = output.ports().first() {
+            output.connect(port.clone());
+        }
+    }
+}
+
+// Each new voice climbs the scale, and alternates between a few rhythms
+fn spawn_voice(mut commands: Commands, keys: Res<ButtonInput<KeyCode>>, voices: Query<&Pitch>) {
+    if !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    let count = voices.iter().count();
+    let degree = (count * 2) as u8;
+    let Some(pitch) = ScaleDegree::to_midi_note(ROOT, SCALE, degree, -1) else {
+        return;
+    };
+    let rhythms = [
+        CLOCKS_PER_BEAT,
+        CLOCKS_PER_BEAT / 2,
+        CLOCKS_PER_BEAT * 3 / 4,
+    ];
+    commands.spawn((
+        Pitch(pitch),
+        Rhythm(rhythms[count % rhythms.len()]),
+        Velocity(100 - (count % 4) as u8 * 15),
+    ));
+}
+
+fn despawn_voice(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    voices: Query<(Entity, &Pitch)>,
+    output: Res<MidiOutput>,
+) {
+    if !keys.just_pressed(KeyCode::Backspace) {
+        return;
+    }
+    if let Some((entity, pitch)) = voices.iter().max_by_key(|(entity, _)| *entity) {
+        output.note_off(CHANNEL, pitch.0, 0);
+        commands.entity(entity).despawn();
+    }
+}
+
+fn play_voices(
+    clock: Res<MidiClock>,
+    output: Res<MidiOutput>,
+    voices: Query<(&Pitch, &Rhythm, &Velocity)>,
+    mut last_tick: Local<u64>,
+) {
+    if !clock.is_running() {
+        *last_tick = clock.ticks();
+        return;
+    }
+    // Several ticks can arrive in one frame
+    for tick in (*last_tick + 1)..=clock.ticks() {
+        for (pitch, rhythm, velocity) in &voices {
+            if tick % rhythm.0 == 0 {
+                output.note_off(CHANNEL, pitch.0, 0);
+                output.note_on(CHANNEL, pitch.0, velocity.0);
+            }
+        }
+    }
+    *last_tick = clock.ticks();
+}
+
+#[derive(Component)]
+struct Status;
+
+fn show_status(
+    clock: Res<MidiClock>,
+    voices: Query<&Pitch>,
+    mut status: Query<&mut Text, With<Status>>,
+) {
+    let bpm = match clock.bpm() {
+        Some(bpm) => format!("{:.1} bpm", bpm),
+        None => "no clock".to_string(),
+    };
+    let notes: Vec<String> = voices
+        .iter()
+        .filter_map(|p| MidiNote::try_from(p.0).ok())
+        .map(|note| note.to_string())
+        .collect();
+    status.single_mut().sections[1].value = format!(
+        "{}, beat {}\nVoices: {}",
+        bpm,
+        clock.beat(),
+        notes.join(" ")
+    );
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "INSTRUCTIONS \n\
+                Space - Add a voice \n\
+                Backspace - Remove the newest voice \n\n",
+                TextStyle {
+                    font_size: 30.0,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: 30.0,
+                ..default()
+            }),
+        ]),
+        Status,
+    ));
+}
@@ -0,0 +1,93 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+};
+use bevy_midi::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(MaterialPlugin::<NotesMaterial>::default())
+        .add_plugins(MidiInputPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (connect_to_first_port, update_uniforms))
+        .run();
+}
+
+// The checks generated by `#[derive(ShaderType)]` are reported as dead code by newer compilers
+#[allow(dead_code)]
+mod uniforms {
+    use super::*;
+
+    /// Uniforms read by `assets/shaders/notes.wgsl`.
+    ///
+    /// Uniform arrays need a 16 byte stride, so the 128 values of `cc_values`
+    /// are packed four to a [`Vec4`].
+    #[derive(ShaderType, Clone, Debug)]
+    pub struct NoteUniforms {
+        pub cc_values: [Vec4; 32],
+    }
+}
+use uniforms::NoteUniforms;
+
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+struct NotesMaterial {
+    #[uniform(0)]
+    uniforms: NoteUniforms,
+}
+
+impl Material for NotesMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/notes.wgsl".into()
+    }
+}
+
+#[derive(Resource)]
+struct NotesMaterialHandle(Handle<NotesMaterial>);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<NotesMaterial>>,
+) {
+    let material = materials.add(NotesMaterial {
+        uniforms: NoteUniforms {
+            cc_values: [Vec4::ZERO; 32],
+        },
+    });
+    commands.insert_resource(NotesMaterialHandle(material.clone()));
+
+    commands.spawn(MaterialMeshBundle {
+        mesh: meshes.add(Rectangle::new(4.0, 2.0)),
+        material,
+        ..default()
+    });
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 0.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn connect_to_first_port(input: Res<MidiInput>) {
+    if input.is_changed() {
+        if let Some((_, port)) = input.ports().first() {
+            input.connect(port.clone());
+        }
+    }
+}
+
+fn update_uniforms(
+    cc_state: Res<MidiCcState>,
+    handle: Res<NotesMaterialHandle>,
+    mut materials: ResMut<Assets<NotesMaterial>>,
+) {
+    if !cc_state.is_changed() {
+        return;
+    }
+    let Some(material) = materials.get_mut(&handle.0) else {
+        return;
+    };
+    for (cc, value) in cc_state.channel(0).iter().enumerate() {
+        material.uniforms.cc_values[cc / 4][cc % 4] = f32::from(*value) / 127.0;
+    }
+}
@@ -0,0 +1,85 @@
+use super::input::MidiData;
+use bevy::prelude::*;
+use bevy_midi_core::{NOTE_OFF_STATUS, NOTE_ON_STATUS, POLY_AFTERTOUCH_STATUS};
+
+/// [`Component`] processing the incoming midi data of one channel, like a
+/// channel strip on a mixing desk.
+///
+/// Every [`MidiData`] event is re-emitted as [`ProcessedMidiData`], after being
+/// transformed by all strips whose [`channel`](MidiChannelStrip::channel) matches.
+/// Messages on channels without a strip pass through unchanged.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct MidiChannelStrip {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    /// Factor applied to NoteOn velocities. Notes are never scaled down to
+    /// velocity 0, which would turn them into NoteOffs.
+    pub velocity_scale: f32,
+    /// Semitones added to note keys. Notes transposed out of range are dropped.
+    pub transpose: i8,
+    /// When `false`, all messages on the channel are dropped.
+    pub enabled: bool,
+}
+
+impl Default for MidiChannelStrip {
+    fn default() -> Self {
+        Self {
+            channel: 0,
+            velocity_scale: 1.0,
+            transpose: 0,
+            enabled: true,
+        }
+    }
+}
+
+impl MidiChannelStrip {
+    fn process(&self, mut data: MidiData) -> Option<MidiData> {
+        if !self.enabled {
+            return None;
+        }
+        let is_note_on = data.message.is_note_on();
+        let [status, key, velocity] = &mut data.message.msg;
+        match *status & 0b1111_0000 {
+            NOTE_ON_STATUS | NOTE_OFF_STATUS | POLY_AFTERTOUCH_STATUS => {
+                let transposed = i16::from(*key) + i16::from(self.transpose);
+                *key = u8::try_from(transposed).ok().filter(|k| *k < 128)?;
+            }
+            _ => {}
+        }
+        if is_note_on && *velocity > 0 {
+            *velocity = (f32::from(*velocity) * self.velocity_scale)
+                .round()
+                .clamp(1.0, 127.0) as u8;
+        }
+        Some(data)
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for incoming midi data after it has been
+/// processed by the [`MidiChannelStrip`]s.
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Debug, Event)]
+pub struct ProcessedMidiData(pub MidiData);
+
+pub(crate) fn process_channel_strips(
+    strips: Query<&MidiChannelStrip>,
+    mut midi: EventReader<MidiData>,
+    mut processed: EventWriter<ProcessedMidiData>,
+) {
+    for data in midi.read() {
+        // System messages have no channel
+        if data.message.msg[0] >= 0xF0 {
+            processed.send(ProcessedMidiData(data.clone()));
+            continue;
+        }
+        let channel = data.message.channel();
+        let data = strips
+            .iter()
+            .filter(|strip| strip.channel == channel)
+            .try_fold(data.clone(), |data, strip| strip.process(data));
+        if let Some(data) = data {
+            processed.send(ProcessedMidiData(data));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use super::input::{reply, MidiData};
+use super::sequencer::{run_sequencer, MidiSequencer};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+const TIMING_CLOCK: u8 = 0xF8;
+const START: u8 = 0xFA;
+const CONTINUE: u8 = 0xFB;
+const STOP: u8 = 0xFC;
+
+/// Number of midi clock messages per quarter note.
+pub const CLOCKS_PER_BEAT: u64 = 24;
+
+// If no clock message arrives for this long, the clock is considered absent
+const CLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Plugin tracking incoming midi clock messages in the [`MidiClock`] resource.
+///
+/// Requires [`MidiInputPlugin`](crate::input::MidiInputPlugin).
+#[derive(Default)]
+pub struct MidiClockPlugin {
+    sync_fixed_timestep: bool,
+}
+
+impl MidiClockPlugin {
+    /// Create a plugin that also sets the timestep of [`Time<Fixed>`] to one
+    /// beat of the incoming clock, so [`FixedUpdate`] runs exactly once per beat.
+    ///
+    /// When no clock is received, the timestep configured before startup is restored.
+    #[must_use]
+    pub fn sync_fixed_timestep() -> Self {
+        MidiClockPlugin {
+            sync_fixed_timestep: true,
+        }
+    }
+}
+
+impl Plugin for MidiClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MidiClock>().add_systems(
+            PreUpdate,
+            (
+                update_clock.after(reply),
+                run_sequencer
+                    .after(update_clock)
+                    .run_if(resource_exists::<MidiSequencer>),
+            ),
+        );
+        if self.sync_fixed_timestep {
+            app.add_systems(PreUpdate, sync_fixed_timestep.after(update_clock));
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the state of the incoming midi clock.
+///
+/// Updated from [`PreUpdate`] by [`MidiClockPlugin`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct MidiClock {
+    running: bool,
+    ticks: u64,
+    bpm: Option<f32>,
+    last_stamp: Option<u64>,
+    last_seen: Option<Duration>,
+    intervals: VecDeque<u64>,
+}
+
+impl MidiClock {
+    /// Check whether a start or continue message was received, and no stop since.
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Get the tempo of the incoming clock in quarter notes per minute, if a clock is present.
+    #[must_use]
+    pub fn bpm(&self) -> Option<f32> {
+        self.bpm
+    }
+
+    /// Get the number of clock messages received since the last start message.
+    #[must_use]
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Get the number of whole beats since the last start message.
+    #[must_use]
+    pub fn beat(&self) -> u64 {
+        self.ticks / CLOCKS_PER_BEAT
+    }
+
+    /// Get the position within the current beat, in `0.0..1.0`.
+    #[must_use]
+    pub fn beat_fraction(&self) -> f32 {
+        (self.ticks % CLOCKS_PER_BEAT) as f32 / CLOCKS_PER_BEAT as f32
+    }
+
+    fn tick(&mut self, stamp: u64) {
+        if self.running {
+            self.ticks += 1;
+        }
+        if let Some(last) = self.last_stamp {
+            // Stamps restart when the input reconnects
+            if stamp > last {
+                self.intervals.push_back(stamp - last);
+                if self.intervals.len() > CLOCKS_PER_BEAT as usize {
+                    self.intervals.pop_front();
+                }
+                let average =
+                    self.intervals.iter().sum::<u64>() as f64 / self.intervals.len() as f64;
+                self.bpm = Some((60_000_000.0 / (average * CLOCKS_PER_BEAT as f64)) as f32);
+            }
+        }
+        self.last_stamp = Some(stamp);
+    }
+}
+
+fn update_clock(
+    mut clock: ResMut<MidiClock>,
+    mut midi: EventReader<MidiData>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed();
+    for data in midi.read() {
+        match data.message.msg[0] {
+            TIMING_CLOCK => {
+                clock.tick(data.stamp);
+                clock.last_seen = Some(now);
+            }
+            START => {
+                clock.running = true;
+                clock.ticks = 0;
+            }
+            CONTINUE => clock.running = true,
+            STOP => clock.running = false,
+            _ => {}
+        }
+    }
+
+    let timed_out = clock
+        .last_seen
+        .is_some_and(|seen| now.saturating_sub(seen) > CLOCK_TIMEOUT);
+    if timed_out {
+        clock.bpm = None;
+        clock.last_stamp = None;
+        clock.last_seen = None;
+        clock.intervals.clear();
+    }
+}
+
+fn sync_fixed_timestep(
+    clock: Res<MidiClock>,
+    mut fixed: ResMut<Time<Fixed>>,
+    mut default_timestep: Local<Option<Duration>>,
+) {
+    let default_timestep = *default_timestep.get_or_insert(fixed.timestep());
+    let timestep = match clock.bpm {
+        Some(bpm) if bpm > 0.0 => Duration::from_secs_f64(60.0 / f64::from(bpm)),
+        _ => default_timestep,
+    };
+    if fixed.timestep() != timestep {
+        fixed.set_timestep(timestep);
+    }
+}
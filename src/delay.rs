@@ -0,0 +1,146 @@
+use super::input::MidiData;
+use super::output::MidiOutput;
+use super::MidiMessage;
+use bevy::prelude::*;
+use std::time::{Duration, Instant};
+
+/// The most echoes a [`MidiDelay`] plays of a single note.
+pub const MAX_DELAY_TAPS: u32 = 32;
+
+/// [`Resource`](bevy::ecs::system::Resource) echoing incoming notes to
+/// [`MidiOutput`], like a delay line.
+///
+/// While this resource and [`MidiOutput`] are present, every incoming NoteOn and
+/// NoteOff is sent again every `delay_ms` milliseconds. Each echo's velocity is the
+/// previous one's scaled by `feedback`, until it rounds down to `0` or
+/// [`MAX_DELAY_TAPS`] echoes were sent. Other messages aren't echoed.
+///
+/// Echoes are sent from [`PreUpdate`], so they are rounded to the frame time.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiDelay {
+    pub delay_ms: u64,
+    /// Velocity scale of each echo, in `0.0..1.0`.
+    pub feedback: f32,
+    // Original messages waiting to be echoed, with when and which echo is due
+    pending: Vec<(Instant, u32, MidiMessage)>,
+}
+
+impl MidiDelay {
+    #[must_use]
+    pub fn new(delay_ms: u64, feedback: f32) -> Self {
+        MidiDelay {
+            delay_ms,
+            feedback,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Drop all echoes that weren't sent yet.
+    ///
+    /// Notes whose NoteOn was already echoed may be left playing.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    // Get the `tap`th echo of `message`, if it is still audible
+    fn echo(&self, message: MidiMessage, tap: u32) -> Option<MidiMessage> {
+        if tap > MAX_DELAY_TAPS {
+            return None;
+        }
+        let [status, key, velocity] = message.msg;
+        let gain = self.feedback.clamp(0.0, 1.0).powi(tap as i32);
+        // Releases, including NoteOns with a velocity of 0, are echoed as long as
+        // a NoteOn at full velocity would be, so no echo is left playing
+        let reference = if message.is_note_on() && velocity > 0 {
+            velocity
+        } else {
+            127
+        };
+        if (f32::from(reference) * gain).round() < 1.0 {
+            return None;
+        }
+        let velocity = (f32::from(velocity) * gain).round() as u8;
+        Some(MidiMessage {
+            msg: [status, key, velocity],
+        })
+    }
+}
+
+impl Default for MidiDelay {
+    fn default() -> Self {
+        MidiDelay::new(250, 0.5)
+    }
+}
+
+pub(crate) fn delay_midi(
+    mut delay: ResMut<MidiDelay>,
+    output: Option<Res<MidiOutput>>,
+    mut midi: EventReader<MidiData>,
+) {
+    let Some(output) = output else {
+        midi.clear();
+        return;
+    };
+    let now = Instant::now();
+    let interval = Duration::from_millis(delay.delay_ms);
+    for data in midi.read() {
+        if data.message.is_note_on() || data.message.is_note_off() {
+            delay.pending.push((now + interval, 1, data.message));
+        }
+    }
+
+    let (mut due, pending): (Vec<_>, Vec<_>) =
+        delay.pending.drain(..).partition(|(at, _, _)| *at <= now);
+    delay.pending = pending;
+    due.sort_by_key(|(at, _, _)| *at);
+    for (at, tap, message) in due {
+        // Echoes are scaled from the original message, so rounding doesn't add up
+        if let Some(echo) = delay.echo(message, tap) {
+            output.send(echo);
+            delay.pending.push((at + interval, tap + 1, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taps(delay: &MidiDelay, message: MidiMessage) -> u32 {
+        (1..)
+            .take_while(|tap| delay.echo(message, *tap).is_some())
+            .count() as u32
+    }
+
+    #[test]
+    fn releases_are_echoed_as_long_as_the_loudest_note() {
+        let delay = MidiDelay::new(100, 0.5);
+        let loudest = taps(&delay, MidiMessage::note_on(0, 60, 127));
+        assert!(loudest > 0);
+        assert!(taps(&delay, MidiMessage::note_on(0, 60, 10)) <= loudest);
+        assert_eq!(taps(&delay, MidiMessage::note_off(0, 60, 0)), loudest);
+        assert_eq!(taps(&delay, MidiMessage::note_on(0, 60, 0)), loudest);
+    }
+
+    #[test]
+    fn note_on_with_zero_velocity_stays_a_release() {
+        let delay = MidiDelay::new(100, 0.5);
+        let echo = delay.echo(MidiMessage::note_on(0, 60, 0), 1);
+        assert_eq!(echo, Some(MidiMessage::note_on(0, 60, 0)));
+    }
+
+    #[test]
+    fn echoes_decay() {
+        let delay = MidiDelay::new(100, 0.5);
+        let message = MidiMessage::note_on(0, 60, 100);
+        assert_eq!(
+            delay.echo(message, 1),
+            Some(MidiMessage::note_on(0, 60, 50))
+        );
+        assert_eq!(
+            delay.echo(message, 2),
+            Some(MidiMessage::note_on(0, 60, 25))
+        );
+        assert_eq!(delay.echo(message, 8), None);
+    }
+}
@@ -0,0 +1,64 @@
+use super::input::{reply, MidiData, MidiInput, MidiInputConnection};
+use super::output::MidiOutputConnection;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+/// Plugin reporting midi [`Diagnostic`]s, which show up in
+/// [`LogDiagnosticsPlugin`](bevy::diagnostic::LogDiagnosticsPlugin) and other
+/// diagnostics views.
+///
+/// Requires [`MidiInputPlugin`](crate::input::MidiInputPlugin). Output ports are
+/// counted as active connections if [`MidiOutputPlugin`](crate::output::MidiOutputPlugin)
+/// is added too.
+pub struct MidiDiagnosticsPlugin;
+
+impl Plugin for MidiDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::EVENTS_PER_SECOND))
+            .register_diagnostic(Diagnostic::new(Self::EVENTS_DROPPED).with_smoothing_factor(0.0))
+            .register_diagnostic(
+                Diagnostic::new(Self::ACTIVE_CONNECTIONS).with_smoothing_factor(0.0),
+            )
+            .register_diagnostic(Diagnostic::new(Self::LAST_EVENT_LATENCY).with_suffix("ms"))
+            .add_systems(PreUpdate, Self::diagnostic_system.after(reply));
+    }
+}
+
+impl MidiDiagnosticsPlugin {
+    /// [`MidiData`] events received per second.
+    pub const EVENTS_PER_SECOND: DiagnosticPath =
+        DiagnosticPath::const_new("midi/events_per_second");
+    /// Total number of messages dropped, see [`MidiInput::dropped_count`].
+    pub const EVENTS_DROPPED: DiagnosticPath = DiagnosticPath::const_new("midi/events_dropped");
+    /// Number of connected input and output ports.
+    pub const ACTIVE_CONNECTIONS: DiagnosticPath =
+        DiagnosticPath::const_new("midi/active_connections");
+    /// Latency of the last received message, see [`MidiInput::last_latency`].
+    pub const LAST_EVENT_LATENCY: DiagnosticPath =
+        DiagnosticPath::const_new("midi/last_event_latency");
+
+    pub fn diagnostic_system(
+        mut diagnostics: Diagnostics,
+        time: Res<Time<Real>>,
+        input: Res<MidiInput>,
+        input_connection: Res<MidiInputConnection>,
+        output_connection: Option<Res<MidiOutputConnection>>,
+        mut midi: EventReader<MidiData>,
+    ) {
+        let connections = usize::from(input_connection.is_connected())
+            + output_connection.map_or(0, |c| c.connection_count());
+        diagnostics.add_measurement(&Self::ACTIVE_CONNECTIONS, || connections as f64);
+
+        diagnostics.add_measurement(&Self::EVENTS_DROPPED, || input.dropped_count() as f64);
+        if let Some(latency) = input.last_latency() {
+            diagnostics
+                .add_measurement(&Self::LAST_EVENT_LATENCY, || latency.as_secs_f64() * 1000.0);
+        }
+
+        let events = midi.read().count();
+        let delta_seconds = time.delta_seconds_f64();
+        if delta_seconds > 0.0 {
+            diagnostics.add_measurement(&Self::EVENTS_PER_SECOND, || events as f64 / delta_seconds);
+        }
+    }
+}
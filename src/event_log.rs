@@ -0,0 +1,103 @@
+use super::input::MidiData;
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// [`Resource`](bevy::ecs::system::Resource) keeping the last `capacity` incoming
+/// [`MidiData`] events, for inspecting what preceded a bug in midi-driven logic.
+///
+/// Events are only recorded while this resource is present, so insert it to enable logging.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiEventLog {
+    capacity: usize,
+    entries: VecDeque<MidiEventLogEntry>,
+}
+
+/// An event recorded by [`MidiEventLog`].
+#[derive(Clone, Debug)]
+pub struct MidiEventLogEntry {
+    /// The [`FrameCount`] when the event was received, or `0` without [`bevy::core::FrameCountPlugin`].
+    pub frame: u32,
+    /// The wall-clock time when the event was received by the app.
+    pub time: SystemTime,
+    pub data: MidiData,
+}
+
+impl Default for MidiEventLog {
+    fn default() -> Self {
+        MidiEventLog::new(256)
+    }
+}
+
+impl MidiEventLog {
+    /// Create an empty log keeping up to `capacity` events.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        MidiEventLog {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Get the maximum number of events kept.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Iterate over the recorded events, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &MidiEventLogEntry> {
+        self.entries.iter()
+    }
+
+    /// Format the recorded events, oldest first, one per line.
+    #[must_use]
+    pub fn dump_to_string(&self) -> String {
+        let mut dump = String::new();
+        for entry in &self.entries {
+            let time = entry
+                .time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let _ = writeln!(
+                dump,
+                "frame {} at {:.3}: [{}] {}",
+                entry.frame, time, entry.data.stamp, entry.data.message
+            );
+        }
+        dump
+    }
+
+    /// Remove all recorded events.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn push(&mut self, entry: MidiEventLogEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+pub(crate) fn record_events(
+    mut log: ResMut<MidiEventLog>,
+    mut midi: EventReader<MidiData>,
+    frame: Option<Res<FrameCount>>,
+) {
+    let frame = frame.map_or(0, |f| f.0);
+    for data in midi.read() {
+        log.push(MidiEventLogEntry {
+            frame,
+            time: SystemTime::now(),
+            data: data.clone(),
+        });
+    }
+}
@@ -1,44 +1,335 @@
-use super::{MidiMessage, KEY_RANGE};
+use super::channel_strip::{process_channel_strips, ProcessedMidiData};
+use super::delay::{delay_midi, MidiDelay};
+use super::event_log::{record_events, MidiEventLog};
+use super::note_hold::{track_note_holds, NoteHoldTracker, NoteReleasedEvent};
+use super::quantize::NoteQuantizer;
+use super::router::{route_midi, MidiRouter};
+use super::rpn::{decode_rpn, NrpnEvent, RpnEvent};
+use super::state::{
+    emit_cc_deltas, update_aftertouch_state, update_cc_state, update_channel_pressure_state,
+    update_note_histogram, AfterTouchEvent, ChannelPressureEvent, MidiAfterTouchState,
+    MidiCcDeltaEvent, MidiCcState, MidiChannelPressureState, MidiNoteHistogram,
+};
+use super::sustain::{track_sustain, SustainPedalTracker, SustainedMidiData};
+use super::sysex::{MidiSysExData, MmcEvent, SysExBuffer};
+use super::{io_task_pool, MidiMessage, MidiNote};
 use bevy::prelude::Plugin;
-use bevy::{prelude::*, tasks::IoTaskPool};
-use crossbeam_channel::{Receiver, Sender};
+use bevy::utils::tracing::{event, level_filters::LevelFilter};
+use bevy::{log::Level, prelude::*};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use midir::ConnectErrorKind; // XXX: do we expose this?
-pub use midir::{Ignore, MidiInputPort};
+pub use midir::Ignore;
 use std::error::Error;
 use std::fmt::Display;
 use std::future::Future;
-use MidiInputError::{ConnectionError, PortRefreshError};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use MidiInputError::{ConnectionError, DuplicateNoteOn, PortRefreshError};
 
+/// Plugin receiving midi input into [`MidiInput`] and [`MidiData`] events.
+///
+/// The connection to the midi backend is set up from [`Startup`], so that
+/// [`MidiInput`] is available to the first update. When the plugin is added to an
+/// app that already ran its startup schedules, it is set up at the start of the
+/// next [`PreUpdate`] instead, and systems in earlier schedules must not rely on
+/// [`MidiInput`] until then.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_midi::prelude::*;
+/// let mut app = App::new();
+/// app.add_plugins((MinimalPlugins, MidiInputPlugin));
+/// app.update();
+/// assert!(app.world().contains_resource::<MidiInput>());
+/// ```
 pub struct MidiInputPlugin;
 
 impl Plugin for MidiInputPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MidiInputSettings>()
             .init_resource::<MidiInputConnection>()
+            .init_resource::<MidiInputFilter>()
+            .init_resource::<MidiCcState>()
+            .init_resource::<MidiAfterTouchState>()
+            .init_resource::<MidiChannelPressureState>()
+            .init_resource::<MidiNoteHistogram>()
             .add_event::<MidiInputError>()
             .add_event::<MidiData>()
+            .add_event::<NoteOffEvent>()
+            .add_event::<MidiPortAddedEvent>()
+            .add_event::<MidiPortRemovedEvent>()
+            .add_event::<RpnEvent>()
+            .add_event::<NrpnEvent>()
+            .add_event::<ProcessedMidiData>()
+            .add_event::<MidiSysExData>()
+            .add_event::<MmcEvent>()
+            .add_event::<SustainedMidiData>()
+            .add_event::<NoteReleasedEvent>()
+            .add_event::<MidiCcDeltaEvent>()
+            .add_event::<AfterTouchEvent>()
+            .add_event::<ChannelPressureEvent>()
+            .register_type::<MidiMessage>()
+            .register_type::<MidiData>()
             .add_systems(Startup, setup)
-            .add_systems(PreUpdate, reply)
-            .add_systems(Update, debug);
+            .add_systems(
+                PreUpdate,
+                (
+                    // In case the plugin was added after startup
+                    setup.run_if(not(resource_exists::<MidiInput>)),
+                    reply,
+                    (
+                        decode_rpn,
+                        process_channel_strips,
+                        (update_cc_state, emit_cc_deltas).chain(),
+                        update_aftertouch_state,
+                        update_channel_pressure_state,
+                        update_note_histogram,
+                        track_sustain.run_if(resource_exists::<SustainPedalTracker>),
+                        track_note_holds.run_if(resource_exists::<NoteHoldTracker>),
+                        route_midi.run_if(resource_exists::<MidiRouter>),
+                        delay_midi.run_if(resource_exists::<MidiDelay>),
+                        record_events.run_if(resource_exists::<MidiEventLog>),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, debug.run_if(debug_enabled));
     }
 }
 
 /// Settings for [`MidiInputPlugin`].
 ///
 /// This resource must be added before [`MidiInputPlugin`] to take effect.
-#[derive(Resource, Clone, Debug)]
+#[derive(Resource, Clone)]
 pub struct MidiInputSettings {
-    pub client_name: &'static str,
-    pub port_name: &'static str,
+    pub client_name: String,
+    pub port_name: String,
     pub ignore: Ignore,
+    /// Drop ActiveSense messages (`0xFE`), which some devices send every 300ms
+    /// to show they are still connected. Equivalent to adding
+    /// [`Ignore::ActiveSense`] to [`ignore`](Self::ignore).
+    pub ignore_active_sense: bool,
+    pub reconnect: ReconnectPolicy,
+    pub backend: MidiBackendHint,
+    /// Maximum length of an incoming SysEx message, capped to [`MAX_SYSEX_BUFFER_SIZE`].
+    /// Longer messages are dropped.
+    pub sysex_buffer_size: usize,
+    /// Drop NoteOn messages for keys that are already held, as sent by some buggy
+    /// controllers, emitting [`MidiInputError::DuplicateNoteOn`] instead.
+    pub deduplicate_notes: bool,
+    /// Keep the release velocity of NoteOff messages. When `false`, it is
+    /// normalized to `0`, since most devices send a meaningless value.
+    pub preserve_note_off_velocity: bool,
+    /// How many midi messages are emitted per frame.
+    pub batch_mode: BatchMode,
+    /// When set, [`MidiInput`] automatically connects to the first port whose name
+    /// is accepted by this function whenever it is disconnected and the ports are
+    /// refreshed, including at startup.
+    pub port_matcher: Option<Arc<PortMatcher>>,
+    /// When set, called from [`PreUpdate`] with every [`MidiInputError`], in
+    /// addition to sending it as an event.
+    pub on_error: Option<Arc<ErrorHandler>>,
+}
+
+/// A function deciding whether to connect to a port by its name, see
+/// [`MidiInputSettings::port_matcher`].
+pub type PortMatcher = dyn Fn(&str) -> bool + Send + Sync;
+
+/// A function called with every midi input error, see [`MidiInputSettings::on_error`].
+pub type ErrorHandler = dyn Fn(MidiInputError) + Send + Sync;
+
+impl std::fmt::Debug for MidiInputSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MidiInputSettings")
+            .field("client_name", &self.client_name)
+            .field("port_name", &self.port_name)
+            .field("ignore", &self.ignore)
+            .field("ignore_active_sense", &self.ignore_active_sense)
+            .field("reconnect", &self.reconnect)
+            .field("backend", &self.backend)
+            .field("sysex_buffer_size", &self.sysex_buffer_size)
+            .field("deduplicate_notes", &self.deduplicate_notes)
+            .field(
+                "preserve_note_off_velocity",
+                &self.preserve_note_off_velocity,
+            )
+            .field("batch_mode", &self.batch_mode)
+            .field("port_matcher", &self.port_matcher.as_ref().map(|_| ".."))
+            .field("on_error", &self.on_error.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+/// Port matchers and error handlers are compared by identity.
+impl PartialEq for MidiInputSettings {
+    fn eq(&self, other: &Self) -> bool {
+        self.client_name == other.client_name
+            && self.port_name == other.port_name
+            && self.ignore == other.ignore
+            && self.ignore_active_sense == other.ignore_active_sense
+            && self.reconnect == other.reconnect
+            && self.backend == other.backend
+            && self.sysex_buffer_size == other.sysex_buffer_size
+            && self.deduplicate_notes == other.deduplicate_notes
+            && self.preserve_note_off_velocity == other.preserve_note_off_velocity
+            && self.batch_mode == other.batch_mode
+            && match (&self.port_matcher, &other.port_matcher) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (&self.on_error, &other.on_error) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
 }
 
+/// The largest supported [`MidiInputSettings::sysex_buffer_size`].
+pub const MAX_SYSEX_BUFFER_SIZE: usize = 4096;
+
 impl Default for MidiInputSettings {
     fn default() -> Self {
         Self {
-            client_name: "bevy_midi", // XXX: change client name? Test examples?
-            port_name: "bevy_midi",
+            client_name: "bevy_midi".to_string(), // XXX: change client name? Test examples?
+            port_name: "bevy_midi".to_string(),
             ignore: Ignore::None,
+            ignore_active_sense: false,
+            reconnect: ReconnectPolicy::default(),
+            backend: MidiBackendHint::default(),
+            sysex_buffer_size: 1024,
+            deduplicate_notes: false,
+            preserve_note_off_velocity: false,
+            batch_mode: BatchMode::default(),
+            port_matcher: None,
+            on_error: None,
+        }
+    }
+}
+
+impl MidiInputSettings {
+    // The messages midir should drop, combining `ignore` and `ignore_active_sense`
+    fn ignored(&self) -> Ignore {
+        if self.ignore_active_sense {
+            self.ignore | Ignore::ActiveSense
+        } else {
+            self.ignore
+        }
+    }
+
+    /// Get a copy of these settings with only the port name replaced.
+    #[must_use]
+    pub fn with_port_name(&self, name: impl Into<String>) -> Self {
+        Self {
+            port_name: name.into(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The midi backend [`MidiInputPlugin`] is expected to use.
+///
+/// midir selects its backend at compile time, so this can't switch backends at
+/// runtime; a warning is logged at startup if the hint doesn't match the compiled
+/// backend. To use JACK, enable the `jack` feature of `bevy_midi`, which replaces
+/// ALSA on Linux and CoreMIDI on macOS.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MidiBackendHint {
+    /// Use whatever backend was compiled in.
+    #[default]
+    Auto,
+    Alsa,
+    Jack,
+    CoreMidi,
+}
+
+impl MidiBackendHint {
+    /// Get the backend `bevy_midi` was compiled with, if it is one of the hinted backends.
+    #[must_use]
+    pub fn compiled() -> Option<Self> {
+        if cfg!(all(feature = "jack", not(target_os = "windows"))) {
+            Some(MidiBackendHint::Jack)
+        } else if cfg!(target_os = "linux") {
+            Some(MidiBackendHint::Alsa)
+        } else if cfg!(any(target_os = "macos", target_os = "ios")) {
+            Some(MidiBackendHint::CoreMidi)
+        } else {
+            None
+        }
+    }
+}
+
+/// How many midi messages [`MidiInput`] turns into [`MidiData`] events per frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BatchMode {
+    /// Emit every message received since the last frame, however many there are.
+    #[default]
+    PerMessage,
+    /// Emit at most this many messages per frame, leaving the rest for the next
+    /// frames. This bounds the work done per frame for dense midi streams, at the
+    /// cost of latency when more messages arrive than are emitted.
+    MaxPerFrame(usize),
+}
+
+impl BatchMode {
+    fn max_per_frame(self) -> usize {
+        match self {
+            BatchMode::PerMessage => usize::MAX,
+            // Never stall the input completely
+            BatchMode::MaxPerFrame(max) => max.max(1),
+        }
+    }
+}
+
+/// How [`MidiInput`] should retry after a connection to a port fails or is lost.
+///
+/// Reconnection attempts look the port up again by name, so a device that is
+/// unplugged and plugged back in is picked up even if its port changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReconnectPolicy {
+    /// Never reconnect automatically.
+    #[default]
+    Never,
+    /// Retry after `initial_ms`, multiplying the delay by `multiplier` after
+    /// every failed attempt, up to `max_ms`.
+    ///
+    /// The delay resets to `initial_ms` after a successful connection.
+    ExponentialBackoff {
+        initial_ms: u64,
+        max_ms: u64,
+        multiplier: f32,
+    },
+}
+
+impl ReconnectPolicy {
+    fn initial_delay(&self) -> Option<Duration> {
+        match *self {
+            ReconnectPolicy::Never => None,
+            ReconnectPolicy::ExponentialBackoff { initial_ms, .. } => {
+                Some(Duration::from_millis(initial_ms))
+            }
+        }
+    }
+
+    fn next_delay(&self, delay: Duration) -> Duration {
+        match *self {
+            ReconnectPolicy::Never => delay,
+            ReconnectPolicy::ExponentialBackoff {
+                max_ms, multiplier, ..
+            } => {
+                let max = Duration::from_millis(max_ms);
+                // Keep the product finite, so it can only overflow to `max`
+                let multiplier = if multiplier.is_nan() {
+                    1.0
+                } else {
+                    f64::from(multiplier).clamp(1.0, f64::MAX)
+                };
+                Duration::try_from_secs_f64(delay.as_secs_f64() * multiplier)
+                    .unwrap_or(max)
+                    .min(max)
+            }
         }
     }
 }
@@ -50,11 +341,32 @@ impl Default for MidiInputSettings {
 #[derive(Resource)]
 pub struct MidiInput {
     receiver: Receiver<Reply>,
+    // Realtime messages, drained before `receiver`
+    realtime_receiver: Receiver<Reply>,
     sender: Sender<Message>,
     ports: Vec<(String, MidiInputPort)>,
+    ports_ready: bool,
+    dropped: u64,
+    last_latency: Option<Duration>,
 }
 
 impl MidiInput {
+    pub(crate) fn new(
+        sender: Sender<Message>,
+        receiver: Receiver<Reply>,
+        realtime_receiver: Receiver<Reply>,
+    ) -> Self {
+        Self {
+            sender,
+            receiver,
+            realtime_receiver,
+            ports: Vec::new(),
+            ports_ready: false,
+            dropped: 0,
+            last_latency: None,
+        }
+    }
+
     /// Update the available input ports.
     ///
     /// This method temporarily disconnects from the current midi port, so
@@ -67,6 +379,20 @@ impl MidiInput {
             .expect("Couldn't refresh input ports");
     }
 
+    /// Update the available input ports, like [`MidiInput::refresh_ports`], and
+    /// get them from the returned future once they are listed.
+    ///
+    /// The future resolves to an empty list if the ports couldn't be listed.
+    /// [`MidiInput::ports`] is still only updated in [`PreUpdate`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn refresh_ports_async(&self) -> impl Future<Output = Vec<(String, MidiInputPort)>> {
+        let (done, ports) = async_channel::bounded(1);
+        self.sender
+            .send(Message::RefreshPortsNotify(done))
+            .expect("Couldn't refresh input ports");
+        async move { ports.recv().await.unwrap_or_default() }
+    }
+
     /// Connects to the given `port`.
     pub fn connect(&self, port: MidiInputPort) {
         self.sender
@@ -74,6 +400,60 @@ impl MidiInput {
             .expect("Failed to connect to port");
     }
 
+    /// Connects to the first port whose name contains `port_name`, blocking the
+    /// calling thread until the connection is made or `timeout` has passed.
+    ///
+    /// This is meant for tools that need to be connected before they carry on,
+    /// e.g. command line tools. Only ports listed in [`MidiInput::ports`] are
+    /// found, and [`MidiInputConnection`] is still only updated in [`PreUpdate`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn try_connect_sync(
+        &self,
+        port_name: &str,
+        timeout: Duration,
+    ) -> Result<(), MidiInputError> {
+        let i = self
+            .port_index_of_name(port_name)
+            .ok_or(ConnectionError(ConnectErrorKind::InvalidPort))?;
+        let (done, result) = crossbeam_channel::bounded(1);
+        self.sender
+            .send(Message::ConnectToPortSync(self.ports[i].1.clone(), done))
+            .expect("Failed to connect to port");
+        result
+            .recv_timeout(timeout)
+            .unwrap_or(Err(ConnectionError(ConnectErrorKind::Other(
+                "timed out connecting to input port",
+            ))))
+    }
+
+    /// Connects to the first port whose name contains `name`.
+    ///
+    /// Returns `false` if there is no such port.
+    pub fn connect_by_name(&self, name: &str) -> bool {
+        match self.port_index_of_name(name) {
+            Some(i) => {
+                self.connect(self.ports[i].1.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Connects to the port with the CoreMIDI `uniqueID` `id`, see
+    /// [`MidiInputPort::unique_id`].
+    ///
+    /// Returns `false` if there is no such port.
+    #[cfg(target_os = "macos")]
+    pub fn connect_by_unique_id(&self, id: i32) -> bool {
+        match self.ports.iter().find(|(_, p)| p.unique_id() == Some(id)) {
+            Some((_, port)) => {
+                self.connect(port.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Disconnects from the current input port.
     pub fn disconnect(&self) {
         self.sender
@@ -82,9 +462,148 @@ impl MidiInput {
     }
 
     /// Get the current input ports, and their names.
+    ///
+    /// The list is empty until the ports were first listed, which can take a few
+    /// frames; see [`PortsRef::is_ready`].
     #[must_use]
-    pub fn ports(&self) -> &Vec<(String, MidiInputPort)> {
-        &self.ports
+    pub fn ports(&self) -> PortsRef<'_> {
+        PortsRef {
+            ports: &self.ports,
+            ready: self.ports_ready,
+        }
+    }
+
+    /// Check whether the ports were listed at least once, so an empty
+    /// [`MidiInput::ports`] means there are no ports rather than that they are
+    /// still being scanned.
+    #[must_use]
+    pub fn ports_ready(&self) -> bool {
+        self.ports_ready
+    }
+
+    /// Get the number of current input ports.
+    #[must_use]
+    pub fn port_count(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Check whether there are any input ports.
+    #[must_use]
+    pub fn has_ports(&self) -> bool {
+        !self.ports.is_empty()
+    }
+
+    /// Get the index of the first port whose name contains `name`.
+    #[must_use]
+    pub fn port_index_of_name(&self, name: &str) -> Option<usize> {
+        self.ports.iter().position(|(n, _)| n.contains(name))
+    }
+
+    /// Get the current input ports as a multi-line string, one `"index: name"` per line.
+    #[must_use]
+    pub fn ports_display(&self) -> String {
+        self.ports
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| format!("{}: {}\n", i, name))
+            .collect()
+    }
+
+    /// Get the number of midi messages that were received but never sent as
+    /// [`MidiData`], because they were rejected by [`MidiInputFilter`] or
+    /// [`MidiInputSettings::deduplicate_notes`].
+    #[must_use]
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Get the time between the arrival of the last midi message and its
+    /// processing in [`PreUpdate`], or `None` if no message has been received yet.
+    #[must_use]
+    pub fn last_latency(&self) -> Option<Duration> {
+        self.last_latency
+    }
+}
+
+/// The input ports returned by [`MidiInput::ports`], dereferencing to a slice of
+/// ports and their names.
+#[derive(Clone, Copy, Debug)]
+pub struct PortsRef<'a> {
+    ports: &'a [(String, MidiInputPort)],
+    ready: bool,
+}
+
+impl PortsRef<'_> {
+    /// Check whether the ports were listed yet, see [`MidiInput::ports_ready`].
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+}
+
+impl std::ops::Deref for PortsRef<'_> {
+    type Target = [(String, MidiInputPort)];
+
+    fn deref(&self) -> &Self::Target {
+        self.ports
+    }
+}
+
+impl<'a> IntoIterator for PortsRef<'a> {
+    type Item = &'a (String, MidiInputPort);
+    type IntoIter = std::slice::Iter<'a, (String, MidiInputPort)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ports.iter()
+    }
+}
+
+/// A midi input port, as listed by [`MidiInput::ports`].
+///
+/// The port's name is looked up when the ports are refreshed and stored
+/// alongside it.
+///
+/// The [`Default`] port is an invalid placeholder with an empty name, which fails
+/// with [`ConnectErrorKind::InvalidPort`] when connected to.
+#[derive(Clone, Default, PartialEq)]
+pub struct MidiInputPort {
+    port: Option<midir::MidiInputPort>,
+    name: Arc<str>,
+}
+
+impl MidiInputPort {
+    /// Get the name of the port.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check whether this is a listed port, rather than the [`Default`] placeholder.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// Get the CoreMIDI `uniqueID` of the port's device, which stays the same
+    /// across reboots, unlike its position in [`MidiInput::ports`].
+    #[cfg(target_os = "macos")]
+    #[must_use]
+    pub fn unique_id(&self) -> Option<i32> {
+        // midir formats the id as an unsigned number, with 0 for a missing id
+        let id = self.port.as_ref()?.id();
+        let id = id
+            .parse::<i32>()
+            .ok()
+            .or_else(|| id.parse::<u32>().ok().map(|id| id as i32))?;
+        (id != 0).then_some(id)
+    }
+}
+
+impl std::fmt::Debug for MidiInputPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MidiInputPort")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
     }
 }
 
@@ -95,6 +614,8 @@ impl MidiInput {
 #[derive(Resource, Default)]
 pub struct MidiInputConnection {
     connected: bool,
+    last_connected_at: Option<Instant>,
+    last_disconnected_at: Option<Instant>,
 }
 
 impl MidiInputConnection {
@@ -102,22 +623,191 @@ impl MidiInputConnection {
     pub fn is_connected(&self) -> bool {
         self.connected
     }
+
+    /// Get when the last connection was made, as seen from [`PreUpdate`].
+    #[must_use]
+    pub fn last_connected_at(&self) -> Option<Instant> {
+        self.last_connected_at
+    }
+
+    /// Get when the last connection was lost or closed, as seen from [`PreUpdate`].
+    ///
+    /// If this is later than [`last_connected_at`](Self::last_connected_at), the
+    /// input is currently disconnected, and any messages sent since were missed.
+    #[must_use]
+    pub fn last_disconnected_at(&self) -> Option<Instant> {
+        self.last_disconnected_at
+    }
 }
 
 /// An [`Event`](bevy::ecs::event::Event) for incoming midi data.
 ///
-/// This event fires from [`CoreStage::PreUpdate`].
-#[derive(Resource, Event)]
+/// This event fires from [`PreUpdate`].
+///
+/// Events are delivered in the order the messages arrived from the device:
+/// the connection's callback is the only producer of midi data, the channel
+/// between it and the app is first-in first-out, and all of it is drained into
+/// the event queue in order. An [`EventReader`] therefore always observes
+/// `MidiData` in arrival order, except for realtime messages (`0xF8..=0xFF`,
+/// e.g. timing clock), which go through their own channel and are delivered
+/// ahead of any messages still waiting to be processed.
+#[derive(Resource, Event, Reflect, Clone, Debug)]
 pub struct MidiData {
     pub stamp: u64,
     pub message: MidiMessage,
 }
 
+/// An [`Event`](bevy::ecs::event::Event) for a released key, sent along with the
+/// [`MidiData`] of every NoteOff message, and of every NoteOn message with a
+/// velocity of `0`.
+///
+/// This event fires from [`PreUpdate`]. `release_velocity` is `0` unless
+/// [`MidiInputSettings::preserve_note_off_velocity`] is set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct NoteOffEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub key: u8,
+    pub release_velocity: u8,
+}
+
+/// An observer [`Event`](bevy::ecs::event::Event) triggered for every [`MidiData`] event.
+///
+/// This allows handling midi data imperatively with an observer instead of an
+/// [`EventReader`]:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_midi::prelude::*;
+/// # let mut world = World::new();
+/// world.observe(|trigger: Trigger<MidiDataTrigger>| {
+///     info!("{:?}", trigger.event().0.message);
+/// });
+/// ```
+#[derive(Clone, Debug, Event)]
+pub struct MidiDataTrigger(pub MidiData);
+
+/// [`Resource`](bevy::ecs::system::Resource) deciding which incoming midi messages
+/// are emitted as [`MidiData`] events.
+///
+/// Messages that don't pass the filter are dropped before they reach any
+/// [`EventReader`]. The default filter passes everything.
+#[derive(Resource, Clone)]
+pub struct MidiInputFilter {
+    predicate: Arc<dyn Fn(&MidiData) -> bool + Send + Sync>,
+}
+
+impl Default for MidiInputFilter {
+    fn default() -> Self {
+        Self::new(|_| true)
+    }
+}
+
+impl std::fmt::Debug for MidiInputFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MidiInputFilter").finish_non_exhaustive()
+    }
+}
+
+impl MidiInputFilter {
+    /// Create a filter passing only the messages for which `predicate` returns `true`.
+    pub fn new(predicate: impl Fn(&MidiData) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Create a filter passing only channel messages whose channel's bit is set
+    /// in `mask`, where bit 0 is channel 0.
+    ///
+    /// System messages always pass.
+    #[must_use]
+    pub fn channel_mask(mask: u16) -> Self {
+        Self::new(move |data| {
+            data.message.msg[0] >= 0xF0 || mask & (1 << data.message.channel()) != 0
+        })
+    }
+
+    /// Check whether `data` should be emitted.
+    #[must_use]
+    pub fn should_pass(&self, data: &MidiData) -> bool {
+        (self.predicate)(data)
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for an input port that appeared since
+/// the ports were last refreshed.
+///
+/// This event fires from [`PreUpdate`]. On the first update, it fires once for
+/// every port available at startup.
+#[derive(Clone, Debug, Event)]
+pub struct MidiPortAddedEvent {
+    pub name: String,
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for an input port that disappeared since
+/// the ports were last refreshed.
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Debug, Event)]
+pub struct MidiPortRemovedEvent {
+    pub name: String,
+}
+
 /// The [`Error`] type for midi input operations, accessible as an [`Event`](bevy::ecs::event::Event).
 #[derive(Clone, Debug, Event)]
 pub enum MidiInputError {
     ConnectionError(ConnectErrorKind),
     PortRefreshError,
+    /// A NoteOn for a key that was already held was dropped, see
+    /// [`MidiInputSettings::deduplicate_notes`]. Holds the channel and key.
+    DuplicateNoteOn(u8, u8),
+}
+
+impl MidiInputError {
+    /// Get the reason a connection failed, if this is a [`MidiInputError::ConnectionError`].
+    #[must_use]
+    pub fn connection_failure_reason(&self) -> Option<ConnectionFailureReason> {
+        match self {
+            ConnectionError(kind) => Some(kind.into()),
+            _ => None,
+        }
+    }
+}
+
+/// Why connecting to a midi port failed, classified from the backend's [`ConnectErrorKind`].
+///
+/// Backends only describe most failures with a message, which is matched against
+/// known wordings; anything else is kept as [`ConnectionFailureReason::Unknown`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConnectionFailureReason {
+    /// The device is already opened exclusively, e.g. by another application.
+    DeviceBusy,
+    /// The port no longer exists, or was never listed.
+    InvalidPort,
+    /// The operating system refused access to the device.
+    PermissionDenied,
+    Unknown(String),
+}
+
+impl From<&ConnectErrorKind> for ConnectionFailureReason {
+    fn from(kind: &ConnectErrorKind) -> Self {
+        let message = match kind {
+            ConnectErrorKind::InvalidPort => return ConnectionFailureReason::InvalidPort,
+            ConnectErrorKind::Other(message) => message,
+        };
+        let lowercase = message.to_lowercase();
+        let contains_any = |words: &[&str]| words.iter().any(|w| lowercase.contains(w));
+        if contains_any(&["busy", "in use", "allocated"]) {
+            ConnectionFailureReason::DeviceBusy
+        } else if contains_any(&["permission", "denied", "not permitted"]) {
+            ConnectionFailureReason::PermissionDenied
+        } else if contains_any(&["invalid port", "no such"]) {
+            ConnectionFailureReason::InvalidPort
+        } else {
+            ConnectionFailureReason::Unknown(message.to_string())
+        }
+    }
 }
 
 impl Error for MidiInputError {}
@@ -133,83 +823,396 @@ impl Display for MidiInputError {
                 }
             },
             PortRefreshError => write!(f, "Couldn't refresh input ports")?,
+            DuplicateNoteOn(channel, key) => write!(
+                f,
+                "Dropped duplicate NoteOn for key {} on channel {}",
+                key, channel
+            )?,
         }
         Ok(())
     }
 }
 
-fn reply(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn reply(
+    mut commands: Commands,
     mut input: ResMut<MidiInput>,
     mut conn: ResMut<MidiInputConnection>,
+    filter: Res<MidiInputFilter>,
+    quantizer: Option<Res<NoteQuantizer>>,
     mut err: EventWriter<MidiInputError>,
     mut midi: EventWriter<MidiData>,
+    mut note_off: EventWriter<NoteOffEvent>,
+    mut added: EventWriter<MidiPortAddedEvent>,
+    mut removed: EventWriter<MidiPortRemovedEvent>,
+    mut sysex: EventWriter<MidiSysExData>,
+    mut mmc: EventWriter<MmcEvent>,
+    settings: Res<MidiInputSettings>,
+    // One bit per key, per channel
+    mut held: Local<[u128; 16]>,
 ) {
-    while let Ok(msg) = input.receiver.try_recv() {
+    // Realtime messages like timing clock are time-critical, so they skip ahead
+    // of any buffered messages
+    let limit = settings.batch_mode.max_per_frame();
+    let mut processed = 0;
+    while processed < limit {
+        let Ok(msg) = input
+            .realtime_receiver
+            .try_recv()
+            .or_else(|_| input.receiver.try_recv())
+        else {
+            break;
+        };
         match msg {
             Reply::AvailablePorts(ports) => {
+                for (name, _) in &input.ports {
+                    if !ports.iter().any(|(n, _)| n == name) {
+                        removed.send(MidiPortRemovedEvent { name: name.clone() });
+                    }
+                }
+                for (name, _) in &ports {
+                    if !input.ports.iter().any(|(n, _)| n == name) {
+                        added.send(MidiPortAddedEvent { name: name.clone() });
+                    }
+                }
                 input.ports = ports;
+                input.ports_ready = true;
+
+                // Auto-connect
+                let matcher = settings.port_matcher.as_ref().filter(|_| !conn.connected);
+                if let Some(matcher) = matcher {
+                    if let Some((_, port)) = input.ports.iter().find(|(name, _)| matcher(name)) {
+                        input.connect(port.clone());
+                    }
+                }
             }
             Reply::Error(e) => {
                 warn!("{}", e);
+                if let Some(on_error) = &settings.on_error {
+                    on_error(e.clone());
+                }
                 err.send(e);
             }
             Reply::Connected => {
                 conn.connected = true;
+                conn.last_connected_at = Some(Instant::now());
             }
             Reply::Disconnected => {
                 conn.connected = false;
+                conn.last_disconnected_at = Some(Instant::now());
+                *held = [0; 16];
             }
-            Reply::Midi(m) => {
-                midi.send(m);
+            Reply::Midi(mut m, received) => {
+                processed += 1;
+                // Statistics don't count as a change to the ports
+                input.bypass_change_detection().last_latency = Some(received.elapsed());
+                if let Some(quantizer) = &quantizer {
+                    // Only notes, clock messages keep their stamps for the tempo estimate
+                    if m.message.is_note_on() || m.message.is_note_off() {
+                        m.stamp = quantizer.quantize(m.stamp);
+                    }
+                }
+                if m.message.is_note_off() && !settings.preserve_note_off_velocity {
+                    m.message.msg[2] = 0;
+                }
+                if settings.deduplicate_notes && (m.message.is_note_on() || m.message.is_note_off())
+                {
+                    let [_, key, velocity] = m.message.msg;
+                    let channel = m.message.channel();
+                    let bit = 1u128 << (key & 0x7F);
+                    let notes = &mut held[usize::from(channel)];
+                    if m.message.is_note_on() && velocity > 0 {
+                        if *notes & bit != 0 {
+                            let e = DuplicateNoteOn(channel, key);
+                            debug!("{}", e);
+                            if let Some(on_error) = &settings.on_error {
+                                on_error(e.clone());
+                            }
+                            err.send(e);
+                            input.bypass_change_detection().dropped += 1;
+                            continue;
+                        }
+                        *notes |= bit;
+                    } else {
+                        *notes &= !bit;
+                    }
+                }
+                if filter.should_pass(&m) {
+                    let [_, key, velocity] = m.message.msg;
+                    if m.message.is_note_off() || (m.message.is_note_on() && velocity == 0) {
+                        note_off.send(NoteOffEvent {
+                            channel: m.message.channel(),
+                            key,
+                            release_velocity: velocity,
+                        });
+                    }
+                    commands.trigger(MidiDataTrigger(m.clone()));
+                    midi.send(m);
+                } else {
+                    input.bypass_change_detection().dropped += 1;
+                }
+            }
+            Reply::SysEx(data) => {
+                if let Some(e) = MmcEvent::from_sysex(&data) {
+                    mmc.send(e);
+                }
+                sysex.send(data);
             }
         }
     }
 }
 
-fn setup(mut commands: Commands, settings: Res<MidiInputSettings>) {
+fn setup(
+    mut commands: Commands,
+    settings: Res<MidiInputSettings>,
+    existing: Option<Res<MidiInput>>,
+) {
+    // Already provided by `testing::FakeMidiInput`, which is installed after
+    // the plugin but before the first update
+    if existing.is_some() {
+        return;
+    }
+
+    let compiled = MidiBackendHint::compiled();
+    if settings.backend != MidiBackendHint::Auto && Some(settings.backend) != compiled {
+        warn!(
+            "Requested the {:?} midi backend, but bevy_midi was compiled for {:?}; \
+            JACK requires the `jack` feature",
+            settings.backend, compiled
+        );
+    }
+
     let (m_sender, m_receiver) = crossbeam_channel::unbounded::<Message>();
     let (r_sender, r_receiver) = crossbeam_channel::unbounded::<Reply>();
+    let (realtime_sender, realtime_receiver) = crossbeam_channel::unbounded::<Reply>();
+
+    // List the ports right away, so they're available from the first update
+    let input = midir::MidiInput::new(&settings.client_name).ok();
+    if let Some(input) = &input {
+        r_sender.send(get_available_ports(input)).unwrap();
+    }
 
-    let thread_pool = IoTaskPool::get();
+    let thread_pool = io_task_pool();
     thread_pool
         .spawn(MidiInputTask {
             receiver: m_receiver,
             sender: r_sender,
+            realtime_sender,
             settings: settings.clone(),
-            input: None,
+            input,
             connection: None,
+            reconnect: None,
         })
         .detach();
 
-    commands.insert_resource(MidiInput {
-        sender: m_sender,
-        receiver: r_receiver,
-        ports: Vec::new(),
-    });
+    commands.insert_resource(MidiInput::new(m_sender, r_receiver, realtime_receiver));
 }
 
-enum Message {
+/// Requests sent from [`MidiInput`] to the background task.
+///
+/// This is crate-private for now, but is intended to become public so that
+/// custom input backends can be implemented; it is `#[non_exhaustive]` so that
+/// variants can keep being added once it is.
+#[non_exhaustive]
+pub(crate) enum Message {
     RefreshPorts,
+    // Also sends the refreshed ports to the given sender
+    RefreshPortsNotify(async_channel::Sender<Vec<(String, MidiInputPort)>>),
     ConnectToPort(MidiInputPort),
+    // Also reports the outcome of the connection to the given sender
+    ConnectToPortSync(MidiInputPort, Sender<Result<(), MidiInputError>>),
     DisconnectFromPort,
 }
 
-enum Reply {
+/// Replies sent from the background task back to [`MidiInput`].
+///
+/// See [`Message`] for why this is crate-private.
+#[non_exhaustive]
+pub(crate) enum Reply {
     AvailablePorts(Vec<(String, MidiInputPort)>),
     Error(MidiInputError),
     Connected,
     Disconnected,
-    Midi(MidiData),
+    // With the time the message arrived from the device
+    Midi(MidiData, Instant),
+    SysEx(MidiSysExData),
 }
 
 struct MidiInputTask {
     receiver: Receiver<Message>,
     sender: Sender<Reply>,
+    realtime_sender: Sender<Reply>,
     settings: MidiInputSettings,
 
     // Invariant: exactly one of `input` or `connection` is Some
     input: Option<midir::MidiInput>,
     connection: Option<(midir::MidiInputConnection<()>, MidiInputPort)>,
+
+    // Name of the port to reconnect to, when to try next, and the current delay
+    reconnect: Option<(String, Instant, Duration)>,
+}
+
+impl MidiInputTask {
+    fn schedule_reconnect(&mut self, name: Option<String>) {
+        let Some(name) = name else {
+            return;
+        };
+        if let Some(delay) = self.settings.reconnect.initial_delay() {
+            self.retry_after(name, delay);
+        }
+    }
+
+    // A delay too long to represent never comes due, so it stops reconnecting
+    fn retry_after(&mut self, name: String, delay: Duration) {
+        self.reconnect = Instant::now()
+            .checked_add(delay)
+            .map(|at| (name, at, delay));
+    }
+
+    fn handle_connect(&mut self, port: MidiInputPort) -> Result<(), ConnectErrorKind> {
+        self.reconnect = None;
+        let was_connected = self.input.is_none();
+        let i = self
+            .input
+            .take()
+            .unwrap_or_else(|| self.connection.take().unwrap().0.close().0);
+        let name = Some(port.name().to_string());
+        match connect(
+            i,
+            &port,
+            &self.settings,
+            self.sender.clone(),
+            self.realtime_sender.clone(),
+        ) {
+            Ok(conn) => {
+                self.sender.send(Reply::Connected).unwrap();
+                self.connection = Some((conn, port));
+                self.input = None;
+                Ok(())
+            }
+            Err(conn_err) => {
+                let kind = conn_err.kind();
+                self.sender
+                    .send(Reply::Error(ConnectionError(kind)))
+                    .unwrap();
+                if was_connected {
+                    self.sender.send(Reply::Disconnected).unwrap();
+                }
+                self.connection = None;
+                self.input = Some(conn_err.into_inner());
+                self.schedule_reconnect(name);
+                Err(kind)
+            }
+        }
+    }
+
+    // Returns the refreshed ports, or an empty list if they couldn't be listed
+    fn handle_refresh_ports(&mut self) -> Vec<(String, MidiInputPort)> {
+        let listed = |reply: &Reply| match reply {
+            Reply::AvailablePorts(ports) => ports.clone(),
+            _ => Vec::new(),
+        };
+        match self.input.take() {
+            Some(i) => {
+                let (i, reply) = self.refresh_ports_recovering(i);
+                let ports = listed(&reply);
+                self.sender.send(reply).unwrap();
+                self.input = Some(i);
+                ports
+            }
+            None => {
+                let (conn, port) = self.connection.take().unwrap();
+                let (i, reply) = self.refresh_ports_recovering(conn.close().0);
+                let ports = listed(&reply);
+
+                self.sender.send(reply).unwrap();
+
+                let name = Some(port.name().to_string());
+                match connect(
+                    i,
+                    &port,
+                    &self.settings,
+                    self.sender.clone(),
+                    self.realtime_sender.clone(),
+                ) {
+                    Ok(conn) => {
+                        self.connection = Some((conn, port));
+                    }
+                    Err(conn_err) => {
+                        self.sender
+                            .send(Reply::Error(ConnectionError(conn_err.kind())))
+                            .unwrap();
+                        self.sender.send(Reply::Disconnected).unwrap();
+                        self.input = Some(conn_err.into_inner());
+                        self.schedule_reconnect(name);
+                    }
+                }
+                ports
+            }
+        }
+    }
+
+    // Some backends panic while listing ports, e.g. CoreMIDI on a poisoned lock
+    // when a device changes state mid-refresh. Instead of taking the task down,
+    // the backend is reinitialized and the refresh retried once.
+    fn refresh_ports_recovering(&self, input: midir::MidiInput) -> (midir::MidiInput, Reply) {
+        let list = |input: &midir::MidiInput| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| get_available_ports(input))).ok()
+        };
+        if let Some(reply) = list(&input) {
+            return (input, reply);
+        }
+        warn!("Midi backend panicked while refreshing input ports, reinitializing it");
+        match midir::MidiInput::new(&self.settings.client_name) {
+            Ok(new_input) => {
+                let reply = list(&new_input).unwrap_or(Reply::Error(PortRefreshError));
+                (new_input, reply)
+            }
+            Err(_) => (input, Reply::Error(PortRefreshError)),
+        }
+    }
+
+    fn try_reconnect(&mut self) {
+        let Some((name, _, delay)) = self.reconnect.take() else {
+            return;
+        };
+        let Some(i) = self.input.take() else {
+            return;
+        };
+
+        let port = i
+            .ports()
+            .into_iter()
+            .find(|p| i.port_name(p).is_ok_and(|n| n == name))
+            .map(|port| MidiInputPort {
+                port: Some(port),
+                name: name.as_str().into(),
+            });
+        let Some(port) = port else {
+            self.input = Some(i);
+            let delay = self.settings.reconnect.next_delay(delay);
+            self.retry_after(name, delay);
+            return;
+        };
+
+        match connect(
+            i,
+            &port,
+            &self.settings,
+            self.sender.clone(),
+            self.realtime_sender.clone(),
+        ) {
+            Ok(conn) => {
+                self.sender.send(Reply::Connected).unwrap();
+                self.connection = Some((conn, port));
+            }
+            Err(conn_err) => {
+                self.input = Some(conn_err.into_inner());
+                let delay = self.settings.reconnect.next_delay(delay);
+                self.retry_after(name, delay);
+            }
+        }
+    }
 }
 
 impl Future for MidiInputTask {
@@ -220,97 +1223,56 @@ impl Future for MidiInputTask {
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
         if self.input.is_none() && self.connection.is_none() {
-            self.input = midir::MidiInput::new(self.settings.client_name).ok();
+            self.input = midir::MidiInput::new(&self.settings.client_name).ok();
             self.sender
                 .send(get_available_ports(self.input.as_ref().unwrap()))
                 .unwrap();
         }
 
-        if let Ok(msg) = self.receiver.recv() {
-            use Message::{ConnectToPort, DisconnectFromPort, RefreshPorts};
+        let msg = match &self.reconnect {
+            Some((_, at, _)) => {
+                let timeout = at.saturating_duration_since(Instant::now());
+                match self.receiver.recv_timeout(timeout) {
+                    Ok(msg) => Some(msg),
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.try_reconnect();
+                        None
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                }
+            }
+            None => self.receiver.recv().ok(),
+        };
+
+        if let Some(msg) = msg {
+            use Message::{
+                ConnectToPort, ConnectToPortSync, DisconnectFromPort, RefreshPorts,
+                RefreshPortsNotify,
+            };
 
             match msg {
                 ConnectToPort(port) => {
-                    let was_connected = self.input.is_none();
-                    let s = self.sender.clone();
-                    let i = self
-                        .input
-                        .take()
-                        .unwrap_or_else(|| self.connection.take().unwrap().0.close().0);
-                    let conn = i.connect(
-                        &port,
-                        self.settings.port_name,
-                        move |stamp, message, _| {
-                            let _ = s.send(Reply::Midi(MidiData {
-                                stamp,
-                                message: [message[0], message[1], message[2]].into(),
-                            }));
-                        },
-                        (),
-                    );
-                    match conn {
-                        Ok(conn) => {
-                            self.sender.send(Reply::Connected).unwrap();
-                            self.connection = Some((conn, port));
-                            self.input = None;
-                        }
-                        Err(conn_err) => {
-                            self.sender
-                                .send(Reply::Error(ConnectionError(conn_err.kind())))
-                                .unwrap();
-                            if was_connected {
-                                self.sender.send(Reply::Disconnected).unwrap();
-                            }
-                            self.connection = None;
-                            self.input = Some(conn_err.into_inner());
-                        }
-                    }
+                    let _ = self.handle_connect(port);
+                }
+                ConnectToPortSync(port, done) => {
+                    let result = self.handle_connect(port).map_err(ConnectionError);
+                    let _ = done.send(result);
                 }
                 DisconnectFromPort => {
+                    self.reconnect = None;
                     if let Some((conn, _)) = self.connection.take() {
                         self.input = Some(conn.close().0);
                         self.connection = None;
                         self.sender.send(Reply::Disconnected).unwrap();
                     }
                 }
-                RefreshPorts => match &self.input {
-                    Some(i) => {
-                        self.sender.send(get_available_ports(i)).unwrap();
-                    }
-                    None => {
-                        let (conn, port) = self.connection.take().unwrap();
-                        let i = conn.close().0;
-
-                        self.sender.send(get_available_ports(&i)).unwrap();
-
-                        let s = self.sender.clone();
-                        let conn = i.connect(
-                            &port,
-                            self.settings.port_name,
-                            move |stamp, message, _| {
-                                let _ = s.send(Reply::Midi(MidiData {
-                                    stamp,
-                                    message: [message[0], message[1], message[2]].into(),
-                                }));
-                            },
-                            (),
-                        );
-                        match conn {
-                            Ok(conn) => {
-                                self.connection = Some((conn, port));
-                                self.input = None;
-                            }
-                            Err(conn_err) => {
-                                self.sender
-                                    .send(Reply::Error(ConnectionError(conn_err.kind())))
-                                    .unwrap();
-                                self.sender.send(Reply::Disconnected).unwrap();
-                                self.connection = None;
-                                self.input = Some(conn_err.into_inner());
-                            }
-                        }
-                    }
-                },
+                RefreshPorts => {
+                    self.handle_refresh_ports();
+                }
+                RefreshPortsNotify(done) => {
+                    let ports = self.handle_refresh_ports();
+                    let _ = done.try_send(ports);
+                }
             }
         }
         cx.waker().wake_by_ref();
@@ -318,6 +1280,94 @@ impl Future for MidiInputTask {
     }
 }
 
+// Helper for above.
+//
+// Connects `input` to `port`, forwarding incoming messages as Reply::Midi.
+fn connect(
+    mut input: midir::MidiInput,
+    port: &MidiInputPort,
+    settings: &MidiInputSettings,
+    sender: Sender<Reply>,
+    realtime_sender: Sender<Reply>,
+) -> Result<midir::MidiInputConnection<()>, midir::ConnectError<midir::MidiInput>> {
+    let Some(port) = &port.port else {
+        return Err(midir::ConnectError::new(
+            ConnectErrorKind::InvalidPort,
+            input,
+        ));
+    };
+    input.ignore(settings.ignored());
+    let mut running_status = None;
+    // Some backends deliver long SysEx messages in several chunks
+    let sysex_limit = settings.sysex_buffer_size.min(MAX_SYSEX_BUFFER_SIZE);
+    let mut sysex = SysExBuffer::<MAX_SYSEX_BUFFER_SIZE>::with_limit(sysex_limit);
+    input.connect(
+        port,
+        &settings.port_name,
+        move |stamp, message, _| {
+            let is_sysex = match message.first() {
+                Some(0xF0) => true,
+                Some(0..=0x7F | 0xF7) => sysex.is_accumulating(),
+                _ => false,
+            };
+            let reply = if is_sysex {
+                running_status = None;
+                if !sysex.push(message) {
+                    return;
+                }
+                let overflowed = sysex.is_overflowed();
+                let data = (!overflowed).then(|| sysex.as_slice().to_vec());
+                sysex.clear();
+                match data {
+                    Some(data) => Reply::SysEx(MidiSysExData { stamp, data }),
+                    None => {
+                        warn!("Dropped a SysEx message longer than {} bytes", sysex_limit);
+                        return;
+                    }
+                }
+            } else {
+                // Any status byte but realtime messages ends an unfinished SysEx message
+                if message.first().is_some_and(|b| *b < 0xF8) {
+                    sysex.clear();
+                }
+
+                // Messages shorter than 3 bytes are padded with zeros
+                let mut msg = [0; 3];
+                // Some hardware omits repeated status bytes (running status)
+                let bytes = match (message.first(), running_status) {
+                    (Some(data), Some(status)) if *data < 0x80 => {
+                        msg[0] = status;
+                        &mut msg[1..]
+                    }
+                    _ => &mut msg[..],
+                };
+                let len = message.len().min(bytes.len());
+                bytes[..len].copy_from_slice(&message[..len]);
+                match msg[0] {
+                    0x80..=0xEF => running_status = Some(msg[0]),
+                    // Realtime messages don't interrupt running status
+                    0xF0..=0xF7 => running_status = None,
+                    _ => {}
+                }
+                Reply::Midi(
+                    MidiData {
+                        stamp,
+                        message: msg.into(),
+                    },
+                    Instant::now(),
+                )
+            };
+            let is_realtime = message.first().is_some_and(|b| *b >= 0xF8);
+            let _ = if is_realtime {
+                realtime_sender.send(reply)
+            } else {
+                sender.send(reply)
+            };
+        },
+        (),
+    )
+}
+
 // Helper for above.
 //
 // Returns either Reply::AvailablePorts or Reply::PortRefreshError
@@ -328,7 +1378,15 @@ fn get_available_ports(input: &midir::MidiInput) -> Reply {
         let ports = input.ports();
         let ports: Result<Vec<_>, _> = ports
             .into_iter()
-            .map(|p| input.port_name(&p).map(|n| (n, p)))
+            .map(|p| {
+                input.port_name(&p).map(|n| {
+                    let port = MidiInputPort {
+                        port: Some(p),
+                        name: n.as_str().into(),
+                    };
+                    (n, port)
+                })
+            })
             .collect();
         if let Ok(ports) = ports {
             return Reply::AvailablePorts(ports);
@@ -337,19 +1395,158 @@ fn get_available_ports(input: &midir::MidiInput) -> Reply {
     Reply::Error(PortRefreshError)
 }
 
-// A system which debug prints note events
+// Skip parsing every message for `debug` when its output would be discarded anyway
+fn debug_enabled() -> bool {
+    LevelFilter::current() >= Level::DEBUG
+}
+
+// A system which debug prints note events, with the message's fields attached
+// as structured key-value pairs that are only formatted if a subscriber records
+// the event
 fn debug(mut midi: EventReader<MidiData>) {
     for data in midi.read() {
-        let pitch = data.message.msg[1];
-        let octave = pitch / 12;
-        let key = KEY_RANGE[pitch as usize % 12];
+        let [_, pitch, velocity] = data.message.msg;
+        let channel = data.message.channel();
+        let note = MidiNote::try_from(pitch).ok();
 
         if data.message.is_note_on() {
-            debug!("NoteOn: {}{:?} - Raw: {:?}", key, octave, data.message.msg);
+            event!(
+                Level::DEBUG,
+                channel,
+                key = pitch,
+                velocity,
+                event_type = "NoteOn",
+                note = note.map(MidiNote::name),
+                octave = note.map(MidiNote::octave),
+                raw = ?data.message.msg,
+            );
         } else if data.message.is_note_off() {
-            debug!("NoteOff: {}{:?} - Raw: {:?}", key, octave, data.message.msg);
+            event!(
+                Level::DEBUG,
+                channel,
+                key = pitch,
+                velocity,
+                event_type = "NoteOff",
+                note = note.map(MidiNote::name),
+                octave = note.map(MidiNote::octave),
+                raw = ?data.message.msg,
+            );
         } else {
-            debug!("Other: {:?}", data.message.msg);
+            event!(Level::DEBUG, event_type = "Other", raw = ?data.message.msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnect_delay_is_capped() {
+        let backoff = |multiplier| ReconnectPolicy::ExponentialBackoff {
+            initial_ms: 100,
+            max_ms: 1000,
+            multiplier,
+        };
+        let delay = Duration::from_millis(100);
+        let max = Duration::from_millis(1000);
+        assert_eq!(backoff(2.0).next_delay(delay), Duration::from_millis(200));
+        assert_eq!(backoff(0.5).next_delay(delay), delay);
+        assert_eq!(backoff(f32::NAN).next_delay(delay), delay);
+        assert_eq!(backoff(f32::INFINITY).next_delay(delay), max);
+        assert_eq!(backoff(f32::MAX).next_delay(Duration::MAX), max);
+    }
+
+    #[test]
+    fn plugin_added_after_startup_sets_up_input() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.update();
+        app.add_plugins(MidiInputPlugin);
+        app.update();
+        assert!(app.world().contains_resource::<MidiInput>());
+    }
+
+    #[test]
+    fn mmc_sysex_emits_mmc_event() {
+        use crate::sysex::{MmcEvent, MmcMessage};
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MidiInputPlugin));
+        let (m_sender, _m_receiver) = crossbeam_channel::unbounded();
+        let (r_sender, r_receiver) = crossbeam_channel::unbounded();
+        let (_realtime_sender, realtime_receiver) = crossbeam_channel::unbounded();
+        app.insert_resource(MidiInput::new(m_sender, r_receiver, realtime_receiver));
+        r_sender
+            .send(Reply::SysEx(MidiSysExData {
+                stamp: 10,
+                data: vec![0xF0, 0x7F, 0x10, 0x06, 0x02, 0xF7],
+            }))
+            .unwrap();
+        app.update();
+
+        let events: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<MmcEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            events,
+            vec![MmcEvent {
+                stamp: 10,
+                device_id: 0x10,
+                message: MmcMessage::Play,
+            }]
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn midi_data_is_delivered_in_order_with_realtime_first() {
+        use crate::testing::FakeMidiInput;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MidiInputPlugin));
+        FakeMidiInput::install(&mut app);
+        let fake = app.world().resource::<FakeMidiInput>().clone();
+        for key in 0..100 {
+            fake.inject(u64::from(key), MidiMessage::note_on(0, key, 100));
         }
+        let clock = MidiMessage::from([0xF8, 0, 0]);
+        fake.inject(100, clock);
+        app.update();
+
+        let received: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<MidiData>>()
+            .drain()
+            .map(|data| data.message)
+            .collect();
+        let mut expected = vec![clock];
+        expected.extend((0..100).map(|key| MidiMessage::note_on(0, key, 100)));
+        assert_eq!(received, expected);
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn quantizer_leaves_clock_stamps_alone() {
+        use crate::clock::{MidiClock, MidiClockPlugin, CLOCKS_PER_BEAT};
+        use crate::quantize::NoteQuantizer;
+        use crate::testing::FakeMidiInput;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MidiInputPlugin, MidiClockPlugin::default()))
+            .insert_resource(NoteQuantizer::default());
+        FakeMidiInput::install(&mut app);
+        let fake = app.world().resource::<FakeMidiInput>().clone();
+        // 120 bpm, a pulse every 20.833ms
+        let interval = 60_000_000 / (120 * CLOCKS_PER_BEAT);
+        for pulse in 0..=CLOCKS_PER_BEAT {
+            fake.inject(pulse * interval, MidiMessage::from([0xF8, 0, 0]));
+        }
+        app.update();
+
+        let bpm = app.world().resource::<MidiClock>().bpm().unwrap();
+        assert!((bpm - 120.0).abs() < 0.1, "{bpm}");
     }
 }
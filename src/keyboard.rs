@@ -0,0 +1,114 @@
+use super::output::MidiOutput;
+use bevy::prelude::*;
+
+/// [`Component`] playing notes on [`MidiOutput`] from the computer keyboard.
+///
+/// Keys are mapped to notes by the [`KeyMapping`] resource, relative to C of
+/// `octave`, with C4 = 60 (middle C). Changing octaves releases all held notes.
+///
+/// Requires [`MidiOutputPlugin`](crate::output::MidiOutputPlugin).
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidiVirtualKeyboard {
+    pub octave: i8,
+    /// Velocity of the NoteOn messages, `0..128`.
+    pub velocity: u8,
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+}
+
+impl Default for MidiVirtualKeyboard {
+    fn default() -> Self {
+        MidiVirtualKeyboard {
+            octave: 4,
+            velocity: 100,
+            channel: 0,
+        }
+    }
+}
+
+impl MidiVirtualKeyboard {
+    /// Get the note played by a key `semitones` above C of the current octave,
+    /// if it is in the midi range.
+    #[must_use]
+    pub fn note(&self, semitones: u8) -> Option<u8> {
+        let note = (i16::from(self.octave) + 1) * 12 + i16::from(semitones);
+        u8::try_from(note).ok().filter(|note| *note < 128)
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) mapping keys to notes for every
+/// [`MidiVirtualKeyboard`].
+///
+/// The default mapping lays out an octave like a piano on the home row, with
+/// `A` as C, `W` as C#, `S` as D and so on up to `K` as the next C, and `Z`/`X`
+/// to shift octaves.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct KeyMapping {
+    /// Keys and the number of semitones above C they play.
+    pub notes: Vec<(KeyCode, u8)>,
+    pub octave_down: KeyCode,
+    pub octave_up: KeyCode,
+}
+
+impl Default for KeyMapping {
+    fn default() -> Self {
+        KeyMapping {
+            notes: vec![
+                (KeyCode::KeyA, 0),
+                (KeyCode::KeyW, 1),
+                (KeyCode::KeyS, 2),
+                (KeyCode::KeyE, 3),
+                (KeyCode::KeyD, 4),
+                (KeyCode::KeyF, 5),
+                (KeyCode::KeyT, 6),
+                (KeyCode::KeyG, 7),
+                (KeyCode::KeyY, 8),
+                (KeyCode::KeyH, 9),
+                (KeyCode::KeyU, 10),
+                (KeyCode::KeyJ, 11),
+                (KeyCode::KeyK, 12),
+            ],
+            octave_down: KeyCode::KeyZ,
+            octave_up: KeyCode::KeyX,
+        }
+    }
+}
+
+pub(crate) fn play_virtual_keyboards(
+    mut keyboards: Query<&mut MidiVirtualKeyboard>,
+    mapping: Res<KeyMapping>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    output: Res<MidiOutput>,
+) {
+    let Some(keys) = keys else {
+        return;
+    };
+    for mut keyboard in &mut keyboards {
+        let shift = i8::from(keys.just_pressed(mapping.octave_up))
+            - i8::from(keys.just_pressed(mapping.octave_down));
+        if shift != 0 {
+            // Release held notes while their octave is still known
+            let held = mapping
+                .notes
+                .iter()
+                .filter(|(key, _)| keys.pressed(*key))
+                .filter_map(|(_, semitones)| keyboard.note(*semitones));
+            for note in held {
+                output.note_off(keyboard.channel, note, 0);
+            }
+            keyboard.octave = (keyboard.octave + shift).clamp(-1, 9);
+            continue;
+        }
+        for (key, semitones) in &mapping.notes {
+            let Some(note) = keyboard.note(*semitones) else {
+                continue;
+            };
+            if keys.just_pressed(*key) {
+                output.note_on(keyboard.channel, note, keyboard.velocity);
+            }
+            if keys.just_released(*key) {
+                output.note_off(keyboard.channel, note, 0);
+            }
+        }
+    }
+}
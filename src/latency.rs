@@ -0,0 +1,127 @@
+use super::input::{reply, MidiData};
+use super::output::MidiOutput;
+use super::MidiMessage;
+use bevy::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Plugin measuring the round-trip latency of midi messages sent with [`MidiOutput`]
+/// until they arrive at [`MidiInput`](crate::input::MidiInput).
+///
+/// Requires [`MidiInputPlugin`](crate::input::MidiInputPlugin) and
+/// [`MidiOutputPlugin`](crate::output::MidiOutputPlugin), with the output connected
+/// to a port that loops back to the connected input. Results are reported in the
+/// [`MidiLatencyDiagnostic`] resource.
+pub struct MidiLatencyProbePlugin;
+
+impl Plugin for MidiLatencyProbePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MidiLatencyProbe>()
+            .init_resource::<MidiLatencyDiagnostic>()
+            .add_systems(PreUpdate, receive_probe.after(reply))
+            .add_systems(Last, send_probe);
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) configuring the probe notes sent by
+/// [`MidiLatencyProbePlugin`].
+///
+/// A NoteOn for `key` on `channel` is sent every `interval`, and the matching NoteOff
+/// as soon as it comes back. Probes that don't come back within `timeout` are dropped.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiLatencyProbe {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub key: u8,
+    pub interval: Duration,
+    pub timeout: Duration,
+    sent_at: Option<Instant>,
+    last_sent_at: Option<Instant>,
+}
+
+impl Default for MidiLatencyProbe {
+    fn default() -> Self {
+        MidiLatencyProbe {
+            channel: 15,
+            key: 0,
+            interval: Duration::from_millis(250),
+            timeout: Duration::from_secs(1),
+            sent_at: None,
+            last_sent_at: None,
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the latencies measured by
+/// [`MidiLatencyProbePlugin`], in microseconds.
+///
+/// Probes are sent at the end of a frame and received in [`PreUpdate`], so the
+/// measured latency is never lower than the time between those two points.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct MidiLatencyDiagnostic {
+    pub min_us: u64,
+    pub max_us: u64,
+    pub avg_us: u64,
+    /// Number of probes that came back.
+    pub samples: u64,
+    /// Number of probes that timed out.
+    pub lost: u64,
+}
+
+impl MidiLatencyDiagnostic {
+    fn record(&mut self, latency_us: u64) {
+        if self.samples == 0 {
+            self.min_us = latency_us;
+            self.max_us = latency_us;
+        } else {
+            self.min_us = self.min_us.min(latency_us);
+            self.max_us = self.max_us.max(latency_us);
+        }
+        self.avg_us = (self.avg_us * self.samples + latency_us) / (self.samples + 1);
+        self.samples += 1;
+    }
+}
+
+fn send_probe(
+    output: Res<MidiOutput>,
+    mut probe: ResMut<MidiLatencyProbe>,
+    mut diagnostic: ResMut<MidiLatencyDiagnostic>,
+) {
+    let now = Instant::now();
+    if let Some(sent_at) = probe.sent_at {
+        if now.duration_since(sent_at) < probe.timeout {
+            return;
+        }
+        diagnostic.lost += 1;
+        probe.sent_at = None;
+    }
+    if probe
+        .last_sent_at
+        .is_some_and(|last| now.duration_since(last) < probe.interval)
+    {
+        return;
+    }
+    output.send(MidiMessage::note_on(probe.channel, probe.key, 1));
+    probe.sent_at = Some(now);
+    probe.last_sent_at = Some(now);
+}
+
+fn receive_probe(
+    output: Res<MidiOutput>,
+    mut probe: ResMut<MidiLatencyProbe>,
+    mut diagnostic: ResMut<MidiLatencyDiagnostic>,
+    mut midi: EventReader<MidiData>,
+) {
+    let now = Instant::now();
+    for data in midi.read() {
+        let Some(sent_at) = probe.sent_at else {
+            continue;
+        };
+        let [_, key, _] = data.message.msg;
+        if data.message.is_note_on() && data.message.channel() == probe.channel && key == probe.key
+        {
+            diagnostic.record(now.duration_since(sent_at).as_micros() as u64);
+            output.send(MidiMessage::note_off(probe.channel, probe.key, 0));
+            probe.sent_at = None;
+        }
+    }
+}
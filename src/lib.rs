@@ -1,18 +1,106 @@
+//! Midi input and output for Bevy, built on [midir](https://github.com/Boddlnagg/midir).
+//!
+//! # Architecture
+//!
+//! [`MidiInputPlugin`](input::MidiInputPlugin) and [`MidiOutputPlugin`](output::MidiOutputPlugin)
+//! each spawn a background task on the [`IoTaskPool`], which owns
+//! the midir connections. The app talks to a task only through a pair of channels:
+//!
+//! - Methods on the [`MidiInput`] and [`MidiOutput`]
+//!   resources, like `connect` or `send`, send a request to the task and return right
+//!   away. The exception is [`MidiInput::try_connect_sync`](input::MidiInput::try_connect_sync),
+//!   which blocks until the task has connected or the timeout expires.
+//! - The task, and midir's callback for incoming messages, send replies back: port
+//!   lists, connection changes, errors and midi data.
+//! - A `reply` system in [`PreUpdate`](bevy::app::PreUpdate) drains the replies,
+//!   updates the resources and emits events. Results of a request are therefore
+//!   visible from the next update on, never within the call.
+//!
+//! For input, the data flows like this:
+//!
+//! ```text
+//! device -> midir callback -> channel -> reply system -> MidiData events -> your systems
+//! ```
+//!
+//! # Requests, events and resources
+//!
+//! - Requests are the crate-private messages sent to a task; use the methods of
+//!   [`MidiInput`] and [`MidiOutput`] to make them.
+//! - [Events](bevy::ecs::event::Event) report what happened: incoming
+//!   [`MidiData`](input::MidiData) and [`MidiSysExData`](sysex::MidiSysExData), port
+//!   changes like [`MidiPortAddedEvent`](input::MidiPortAddedEvent), and errors as
+//!   [`MidiInputError`](input::MidiInputError) and [`MidiOutputError`](output::MidiOutputError).
+//!   Derived events are emitted in `PreUpdate` too: [`NoteOffEvent`](input::NoteOffEvent)
+//!   by the `reply` system itself, alongside the [`MidiData`](input::MidiData), and
+//!   others like [`RpnEvent`](rpn::RpnEvent) by systems running right after it.
+//! - [Resources](bevy::ecs::system::Resource) hold state: the settings, read once at
+//!   startup, such as [`MidiInputSettings`](input::MidiInputSettings), the connection
+//!   status in [`MidiInputConnection`](input::MidiInputConnection) and
+//!   [`MidiOutputConnection`](output::MidiOutputConnection), and accumulated input
+//!   state like [`MidiCcState`](state::MidiCcState). Optional features, like
+//!   [`SustainPedalTracker`](sustain::SustainPedalTracker) or
+//!   [`MidiRouter`](router::MidiRouter), are enabled by inserting their resource.
+//!
+//! Everything is re-exported from [`prelude`].
+
+use bevy::reflect::Reflect;
+use bevy::tasks::IoTaskPool;
+
+pub mod channel_strip;
+pub mod clock;
+pub mod delay;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod event_log;
 pub mod input;
+pub mod keyboard;
+#[cfg(feature = "diagnostics")]
+pub mod latency;
+pub mod note_hold;
+#[cfg(feature = "osc")]
+pub mod osc;
 pub mod output;
+#[cfg(feature = "playback")]
+pub mod playback;
+pub mod quantize;
+pub mod router;
+pub mod rpn;
+pub mod sequencer;
+pub mod state;
+pub mod sustain;
+pub mod sysex;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub mod prelude {
-    pub use crate::{input::*, output::*, *};
+    #[cfg(feature = "diagnostics")]
+    pub use crate::diagnostics::*;
+    #[cfg(feature = "diagnostics")]
+    pub use crate::latency::*;
+    #[cfg(feature = "osc")]
+    pub use crate::osc::*;
+    #[cfg(feature = "playback")]
+    pub use crate::playback::*;
+    pub use crate::{
+        channel_strip::*, clock::*, delay::*, event_log::*, input::*, keyboard::*, note_hold::*,
+        output::*, quantize::*, router::*, rpn::*, sequencer::*, state::*, sustain::*, sysex::*, *,
+    };
 }
 
-pub const KEY_RANGE: [&str; 12] = [
-    "C", "C#/Db", "D", "D#/Eb", "E", "F", "F#/Gb", "G", "G#/Ab", "A", "A#/Bb", "B",
-];
+use input::MidiInput;
+use output::{MidiOutput, MidiOutputHandle};
 
-const NOTE_ON_STATUS: u8 = 0b1001_0000;
-const NOTE_OFF_STATUS: u8 = 0b1000_0000;
+pub use bevy_midi_core::{
+    f32_to_velocity, midi_note, note_frequency, note_name, velocity_to_f32, MidiNote,
+    NoteOutOfRange, Scale, ScaleDegree, KEY_RANGE,
+};
+use bevy_midi_core::{
+    CHANNEL_PRESSURE_STATUS, CONTROL_CHANGE_STATUS, NOTE_OFF_STATUS, NOTE_ON_STATUS,
+    PITCH_BEND_STATUS, POLY_AFTERTOUCH_STATUS, PROGRAM_CHANGE_STATUS,
+};
+use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Reflect)]
 pub struct MidiMessage {
     pub msg: [u8; 3],
 }
@@ -23,7 +111,89 @@ impl From<[u8; 3]> for MidiMessage {
     }
 }
 
+/// For two byte messages like program change, padded with a zero.
+impl From<[u8; 2]> for MidiMessage {
+    fn from([status, data]: [u8; 2]) -> Self {
+        MidiMessage {
+            msg: [status, data, 0],
+        }
+    }
+}
+
+/// For single byte messages like realtime messages, padded with zeros.
+impl From<[u8; 1]> for MidiMessage {
+    fn from([status]: [u8; 1]) -> Self {
+        MidiMessage {
+            msg: [status, 0, 0],
+        }
+    }
+}
+
+/// Formats the message in a human-readable form, e.g. `NoteOn ch:0 key:60 vel:100`
+/// or `CC ch:0 #7=100`. System messages are shown as their raw bytes.
+impl Display for MidiMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let [status, a, b] = self.msg;
+        let ch = self.channel();
+        match status & 0b1111_0000 {
+            NOTE_OFF_STATUS => write!(f, "NoteOff ch:{} key:{} vel:{}", ch, a, b),
+            NOTE_ON_STATUS => write!(f, "NoteOn ch:{} key:{} vel:{}", ch, a, b),
+            POLY_AFTERTOUCH_STATUS => write!(f, "Aftertouch ch:{} key:{} pressure:{}", ch, a, b),
+            CONTROL_CHANGE_STATUS => write!(f, "CC ch:{} #{}={}", ch, a, b),
+            PROGRAM_CHANGE_STATUS => write!(f, "ProgramChange ch:{} program:{}", ch, a),
+            CHANNEL_PRESSURE_STATUS => write!(f, "ChannelPressure ch:{} pressure:{}", ch, a),
+            PITCH_BEND_STATUS => write!(
+                f,
+                "PitchBend ch:{} value:{}",
+                ch,
+                u16::from(a & 0x7F) | u16::from(b & 0x7F) << 7
+            ),
+            _ => write!(f, "System {:02X} {:02X} {:02X}", status, a, b),
+        }
+    }
+}
+
 impl MidiMessage {
+    fn channel_message(status: u8, channel: u8, data1: u8, data2: u8) -> Self {
+        debug_assert!(channel < 16, "midi channel out of range: {}", channel);
+        debug_assert!(data1 < 128, "midi data byte out of range: {}", data1);
+        debug_assert!(data2 < 128, "midi data byte out of range: {}", data2);
+        MidiMessage {
+            msg: [status | (channel & 0x0F), data1 & 0x7F, data2 & 0x7F],
+        }
+    }
+
+    /// Create a NoteOn message. `channel` is in `0..16`; `key` and `velocity` in `0..128`.
+    #[must_use]
+    pub fn note_on(channel: u8, key: u8, velocity: u8) -> Self {
+        Self::channel_message(NOTE_ON_STATUS, channel, key, velocity)
+    }
+
+    /// Create a NoteOff message. `channel` is in `0..16`; `key` and `velocity` in `0..128`.
+    #[must_use]
+    pub fn note_off(channel: u8, key: u8, velocity: u8) -> Self {
+        Self::channel_message(NOTE_OFF_STATUS, channel, key, velocity)
+    }
+
+    /// Create a control change message. `channel` is in `0..16`; `controller` and `value` in `0..128`.
+    #[must_use]
+    pub fn control_change(channel: u8, controller: u8, value: u8) -> Self {
+        Self::channel_message(CONTROL_CHANGE_STATUS, channel, controller, value)
+    }
+
+    /// Create a polyphonic aftertouch message. `channel` is in `0..16`; `key` and
+    /// `pressure` in `0..128`.
+    #[must_use]
+    pub fn aftertouch(channel: u8, key: u8, pressure: u8) -> Self {
+        Self::channel_message(POLY_AFTERTOUCH_STATUS, channel, key, pressure)
+    }
+
+    /// Create a channel pressure message. `channel` is in `0..16`; `pressure` in `0..128`.
+    #[must_use]
+    pub fn channel_pressure(channel: u8, pressure: u8) -> Self {
+        Self::channel_message(CHANNEL_PRESSURE_STATUS, channel, pressure, 0)
+    }
+
     #[must_use]
     pub fn is_note_on(&self) -> bool {
         (self.msg[0] & 0b1111_0000) == NOTE_ON_STATUS
@@ -34,9 +204,58 @@ impl MidiMessage {
         (self.msg[0] & 0b1111_0000) == NOTE_OFF_STATUS
     }
 
+    #[must_use]
+    pub fn is_control_change(&self) -> bool {
+        (self.msg[0] & 0b1111_0000) == CONTROL_CHANGE_STATUS
+    }
+
+    /// Get the length of the message on the wire, from its status byte.
+    ///
+    /// Program change, channel pressure and some system messages are shorter than
+    /// the three bytes stored in [`MidiMessage::msg`].
+    #[must_use]
+    #[allow(clippy::len_without_is_empty)] // A message always has a status byte
+    pub fn len(&self) -> usize {
+        match self.msg[0] {
+            0xC0..=0xDF | 0xF1 | 0xF3 => 2,
+            0xF4..=0xFF => 1,
+            _ => 3,
+        }
+    }
+
+    /// Get the bytes of the message on the wire, without padding.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.msg[..self.len()]
+    }
+
     /// Get the channel of a message, assuming the message is not a system message.
     #[must_use]
     pub fn channel(&self) -> u8 {
         self.msg[0] & 0b0000_1111
     }
 }
+
+/// Connect `input` and `output` to the device whose port names contain `name_fragment`.
+///
+/// This is meant for devices that appear both as an input and an output port,
+/// like hardware synthesizers. Nothing is connected unless both ports are found,
+/// in which case the handle of the new output connection is returned.
+pub fn connect_matching_ports(
+    name_fragment: &str,
+    input: &MidiInput,
+    output: &MidiOutput,
+) -> Option<MidiOutputHandle> {
+    input.port_index_of_name(name_fragment)?;
+    let handle = output.connect_by_name(name_fragment)?;
+    input.connect_by_name(name_fragment);
+    Some(handle)
+}
+
+// The background tasks run on the IoTaskPool, which `TaskPoolPlugin` sets up
+pub(crate) fn io_task_pool() -> &'static IoTaskPool {
+    IoTaskPool::try_get().expect(
+        "bevy_midi needs the IoTaskPool, but it isn't initialized; add `TaskPoolPlugin`, \
+        which is part of `DefaultPlugins` and `MinimalPlugins`",
+    )
+}
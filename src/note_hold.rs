@@ -0,0 +1,71 @@
+use super::input::MidiData;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// [`Resource`](bevy::ecs::system::Resource) that measures how long notes are held.
+///
+/// While this resource is present, a [`NoteReleasedEvent`] is emitted for every
+/// NoteOff (or NoteOn with a velocity of `0`) that ends a note whose NoteOn was
+/// received. Durations are measured between the messages' timestamps, so they
+/// aren't rounded to the frame time.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct NoteHoldTracker {
+    // Channel, key and stamp of every held note
+    held: Vec<(u8, u8, u64)>,
+}
+
+impl NoteHoldTracker {
+    /// Check whether `key` is held on `channel`.
+    #[must_use]
+    pub fn is_held(&self, channel: u8, key: u8) -> bool {
+        self.held.iter().any(|(c, k, _)| *c == channel && *k == key)
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for a released note, with how long it was
+/// held, emitted by the [`NoteHoldTracker`].
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct NoteReleasedEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub key: u8,
+    pub held_duration: Duration,
+    pub release_velocity: u8,
+}
+
+pub(crate) fn track_note_holds(
+    mut tracker: ResMut<NoteHoldTracker>,
+    mut midi: EventReader<MidiData>,
+    mut released: EventWriter<NoteReleasedEvent>,
+) {
+    for data in midi.read() {
+        let message = &data.message;
+        if !message.is_note_on() && !message.is_note_off() {
+            continue;
+        }
+        let channel = message.channel();
+        let [_, key, velocity] = message.msg;
+        let position = tracker
+            .held
+            .iter()
+            .position(|(c, k, _)| *c == channel && *k == key);
+
+        if message.is_note_on() && velocity > 0 {
+            // A re-struck key starts over
+            match position {
+                Some(i) => tracker.held[i].2 = data.stamp,
+                None => tracker.held.push((channel, key, data.stamp)),
+            }
+        } else if let Some(i) = position {
+            let (_, _, pressed) = tracker.held.swap_remove(i);
+            released.send(NoteReleasedEvent {
+                channel,
+                key,
+                held_duration: Duration::from_micros(data.stamp.saturating_sub(pressed)),
+                release_velocity: velocity,
+            });
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use super::input::{MidiData, MidiDataTrigger, MidiInputFilter};
+use super::io_task_pool;
+use bevy::prelude::*;
+use bevy_midi_core::{CONTROL_CHANGE_STATUS, NOTE_OFF_STATUS, NOTE_ON_STATUS};
+use crossbeam_channel::{Receiver, Sender};
+use std::future::Future;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+// How long to wait before receiving again after an error
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Plugin translating incoming OSC (Open Sound Control) messages into [`MidiData`] events.
+///
+/// The following addresses are understood, each taking three `int32` or `float32`
+/// arguments, with channels in `0..16`:
+///
+/// - `/note/on channel key velocity`
+/// - `/note/off channel key velocity`
+/// - `/cc channel controller value`
+///
+/// Float keys, velocities and values in `0.0..=1.0` are scaled to `0..=127`, as
+/// sent by most OSC controllers; other floats are rounded.
+///
+/// Other messages are ignored. Bundles are unpacked, but their time tags are ignored.
+/// Can be used alongside [`MidiInputPlugin`](crate::input::MidiInputPlugin).
+pub struct OscInputPlugin;
+
+impl Plugin for OscInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OscInputSettings>()
+            .add_event::<MidiData>()
+            .add_systems(Startup, setup)
+            .add_systems(PreUpdate, reply);
+    }
+}
+
+/// Settings for [`OscInputPlugin`].
+///
+/// This resource must be added before [`OscInputPlugin`] to take effect.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct OscInputSettings {
+    /// The local UDP address to listen on.
+    pub address: SocketAddr,
+}
+
+impl Default for OscInputSettings {
+    fn default() -> Self {
+        OscInputSettings {
+            address: SocketAddr::from(([127, 0, 0, 1], 9000)),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct OscInput {
+    receiver: Receiver<MidiData>,
+}
+
+fn setup(mut commands: Commands, settings: Res<OscInputSettings>) {
+    let socket = match UdpSocket::bind(settings.address) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Couldn't listen for OSC on {}: {}", settings.address, e);
+            return;
+        }
+    };
+    let (sender, receiver) = crossbeam_channel::unbounded();
+
+    let thread_pool = io_task_pool();
+    thread_pool
+        .spawn(OscInputTask {
+            socket,
+            sender,
+            start: Instant::now(),
+            failing: false,
+        })
+        .detach();
+
+    commands.insert_resource(OscInput { receiver });
+}
+
+fn reply(
+    mut commands: Commands,
+    input: Option<Res<OscInput>>,
+    filter: Option<Res<MidiInputFilter>>,
+    mut midi: EventWriter<MidiData>,
+) {
+    let Some(input) = input else {
+        return;
+    };
+    while let Ok(m) = input.receiver.try_recv() {
+        if filter.as_ref().is_none_or(|f| f.should_pass(&m)) {
+            commands.trigger(MidiDataTrigger(m.clone()));
+            midi.send(m);
+        }
+    }
+}
+
+struct OscInputTask {
+    socket: UdpSocket,
+    sender: Sender<MidiData>,
+    start: Instant,
+    // Whether the last receive failed, so errors are only logged once
+    failing: bool,
+}
+
+impl Future for OscInputTask {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let mut buf = [0; 1536];
+        match self.socket.recv(&mut buf) {
+            Ok(len) => {
+                if self.failing {
+                    info!("Receiving OSC packets again");
+                    self.failing = false;
+                }
+                let stamp = self.start.elapsed().as_micros() as u64;
+                let mut messages = Vec::new();
+                parse_packet(&buf[..len], &mut messages);
+                for msg in messages {
+                    if self
+                        .sender
+                        .send(MidiData {
+                            stamp,
+                            message: msg.into(),
+                        })
+                        .is_err()
+                    {
+                        return std::task::Poll::Ready(());
+                    }
+                }
+            }
+            Err(e) => {
+                if !self.failing {
+                    warn!("Couldn't receive OSC packet: {}", e);
+                    self.failing = true;
+                }
+                // Don't spin on an error that persists
+                std::thread::sleep(RETRY_DELAY);
+            }
+        }
+
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    }
+}
+
+// Helpers for above.
+//
+// Every OSC string is nul terminated and padded to a multiple of 4 bytes.
+fn read_str(data: &[u8]) -> Option<(&str, &[u8])> {
+    let len = data.iter().position(|b| *b == 0)?;
+    let s = std::str::from_utf8(&data[..len]).ok()?;
+    let padded = (len + 4) & !3;
+    Some((s, data.get(padded..)?))
+}
+
+fn read_u32(data: &[u8]) -> Option<(u32, &[u8])> {
+    let bytes = data.get(..4)?;
+    Some((
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        &data[4..],
+    ))
+}
+
+fn parse_packet(data: &[u8], out: &mut Vec<[u8; 3]>) {
+    if let Some(mut rest) = data.strip_prefix(b"#bundle\0") {
+        // Skip the time tag
+        rest = rest.get(8..).unwrap_or_default();
+        while let Some((size, tail)) = read_u32(rest) {
+            let size = size as usize;
+            let Some(element) = tail.get(..size) else {
+                return;
+            };
+            parse_packet(element, out);
+            rest = &tail[size..];
+        }
+    } else if let Some(msg) = parse_message(data) {
+        out.push(msg);
+    }
+}
+
+fn parse_message(data: &[u8]) -> Option<[u8; 3]> {
+    let (address, rest) = read_str(data)?;
+    let status = match address {
+        "/note/on" => NOTE_ON_STATUS,
+        "/note/off" => NOTE_OFF_STATUS,
+        "/cc" => CONTROL_CHANGE_STATUS,
+        _ => return None,
+    };
+    let (tags, mut rest) = read_str(rest)?;
+    let tags = tags.strip_prefix(',')?;
+    if tags.len() != 3 {
+        return None;
+    }
+
+    let mut args = [0u8; 3];
+    for (i, (arg, tag)) in args.iter_mut().zip(tags.chars()).enumerate() {
+        let (value, tail) = read_u32(rest)?;
+        let value = match tag {
+            'i' => value as i32,
+            // Normalized control values, except for the channel
+            'f' if i > 0 && (0.0..=1.0).contains(&f32::from_bits(value)) => {
+                (f32::from_bits(value) * 127.0).round() as i32
+            }
+            'f' => f32::from_bits(value).round() as i32,
+            _ => return None,
+        };
+        // Channels out of range are rejected rather than wrapped to another one
+        if i == 0 && !(0..16).contains(&value) {
+            return None;
+        }
+        *arg = value.clamp(0, 127) as u8;
+        rest = tail;
+    }
+
+    let [channel, a, b] = args;
+    Some([status | channel, a, b])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An OSC message with three arguments of the given type tags
+    fn message_with(address: &str, tags: &str, args: [[u8; 4]; 3]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for s in [address, tags] {
+            data.extend_from_slice(s.as_bytes());
+            data.resize((data.len() / 4 + 1) * 4, 0);
+        }
+        for arg in args {
+            data.extend_from_slice(&arg);
+        }
+        data
+    }
+
+    fn message(address: &str, args: [i32; 3]) -> Vec<u8> {
+        message_with(address, ",iii", args.map(i32::to_be_bytes))
+    }
+
+    #[test]
+    fn parses_note_on() {
+        let data = message("/note/on", [2, 60, 100]);
+        assert_eq!(parse_message(&data), Some([NOTE_ON_STATUS | 2, 60, 100]));
+    }
+
+    #[test]
+    fn rejects_channels_out_of_range() {
+        assert_eq!(parse_message(&message("/note/on", [17, 60, 100])), None);
+        assert_eq!(parse_message(&message("/cc", [16, 1, 64])), None);
+        assert_eq!(parse_message(&message("/cc", [-1, 1, 64])), None);
+    }
+
+    #[test]
+    fn scales_normalized_floats() {
+        let data = message_with(
+            "/cc",
+            ",iff",
+            [
+                1i32.to_be_bytes(),
+                0.5f32.to_be_bytes(),
+                1.0f32.to_be_bytes(),
+            ],
+        );
+        assert_eq!(
+            parse_message(&data),
+            Some([CONTROL_CHANGE_STATUS | 1, 64, 127])
+        );
+        let data = message_with(
+            "/note/on",
+            ",fff",
+            [2.0f32, 60.0, 0.25].map(f32::to_be_bytes),
+        );
+        assert_eq!(parse_message(&data), Some([NOTE_ON_STATUS | 2, 60, 32]));
+        let data = message_with(
+            "/note/on",
+            ",fff",
+            [0.0f32, 59.6, 100.4].map(f32::to_be_bytes),
+        );
+        assert_eq!(parse_message(&data), Some([NOTE_ON_STATUS, 60, 100]));
+    }
+}
@@ -1,11 +1,16 @@
-use super::MidiMessage;
+use super::input::{ConnectionFailureReason, MidiData};
+use super::keyboard::{play_virtual_keyboards, KeyMapping};
+use super::sysex::IDENTITY_REQUEST;
+use super::{io_task_pool, MidiMessage};
+use bevy::ecs::world::Command;
 use bevy::prelude::*;
-use bevy::tasks::IoTaskPool;
+use bevy_midi_core::{CONTROL_CHANGE_STATUS, PROGRAM_CHANGE_STATUS};
 use crossbeam_channel::{Receiver, Sender};
 use midir::ConnectErrorKind;
-pub use midir::MidiOutputPort;
-use std::fmt::Display;
-use std::{error::Error, future::Future};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use MidiOutputError::{ConnectionError, PortRefreshError, SendDisconnectedError, SendError};
 
 pub struct MidiOutputPlugin;
@@ -14,24 +19,57 @@ impl Plugin for MidiOutputPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MidiOutputSettings>()
             .init_resource::<MidiOutputConnection>()
+            .init_resource::<MidiOutputState>()
             .add_event::<MidiOutputError>()
+            .add_event::<SentMidiData>()
+            .register_type::<MidiMessage>()
             .add_systems(Startup, setup)
-            .add_systems(PreUpdate, reply);
+            .add_systems(PreUpdate, (reply, send_scheduled))
+            .init_resource::<KeyMapping>()
+            .add_systems(Update, (panic_button, play_virtual_keyboards));
+        #[cfg(feature = "playback")]
+        app.add_systems(
+            PreUpdate,
+            crate::playback::run_player
+                .after(send_scheduled)
+                .run_if(resource_exists::<crate::playback::MidiPlayer>),
+        );
     }
 }
 
 /// Settings for [`MidiOutputPlugin`].
 ///
 /// This resource must be added before [`MidiOutputPlugin`] to take effect.
-#[derive(Resource, Clone, Debug)]
+#[derive(Resource, Clone, Debug, PartialEq)]
 pub struct MidiOutputSettings {
-    pub port_name: &'static str,
+    pub port_name: String,
+    /// Silently drop messages sent while disconnected, instead of emitting
+    /// [`MidiOutputError::SendDisconnectedError`]. Useful for fire-and-forget usage.
+    pub drop_when_disconnected: bool,
+    /// Emit every midi message sent to at least one port as a [`SentMidiData`] event.
+    pub echo_to_input: bool,
+    /// Maximum change per second of the mix set with [`MidiOutput::set_crossfade`],
+    /// a positive number. Zero, negative and non-finite values, e.g.
+    /// [`f32::INFINITY`], apply changes immediately.
+    pub crossfade_slew_rate: f32,
+    /// Track the control change and program change values sent, in
+    /// [`MidiOutputState`], and send them again to every new connection so that
+    /// a reconnected device picks up where it left off.
+    pub restore_state_on_reconnect: bool,
+    /// Maximum number of notes playing at once on each channel. When a NoteOn
+    /// would exceed it, a NoteOff is sent for the oldest note on that channel first.
+    pub max_polyphony_per_channel: Option<u8>,
 }
 
 impl Default for MidiOutputSettings {
     fn default() -> Self {
         MidiOutputSettings {
-            port_name: "bevy_midi",
+            port_name: "bevy_midi".to_string(),
+            drop_when_disconnected: false,
+            echo_to_input: false,
+            crossfade_slew_rate: 2.0,
+            restore_state_on_reconnect: false,
+            max_polyphony_per_channel: None,
         }
     }
 }
@@ -44,6 +82,14 @@ pub struct MidiOutput {
     sender: Sender<Message>,
     receiver: Receiver<Reply>,
     ports: Vec<(String, MidiOutputPort)>,
+    next_handle: AtomicUsize,
+    tick: u64,
+    // Messages queued by `send_at_tick`, moved to `scheduled` from `PreUpdate`
+    schedule_sender: Sender<(u64, MidiMessage)>,
+    schedule_receiver: Receiver<(u64, MidiMessage)>,
+    scheduled: Vec<(u64, MidiMessage)>,
+    // Copy of the task's rules, so setting them doesn't need `&mut self`
+    routing: Mutex<Vec<RoutingRule>>,
 }
 
 impl MidiOutput {
@@ -54,80 +100,518 @@ impl MidiOutput {
             .expect("Couldn't refresh output ports");
     }
 
-    /// Connect to the given `port`.
-    pub fn connect(&self, port: MidiOutputPort) {
+    /// Connect to the given `port`, in addition to any existing connections.
+    ///
+    /// Returns a handle identifying the new connection, which can be used with
+    /// [`MidiOutput::send_to`]. Connecting to a port that is already connected
+    /// replaces the old connection.
+    pub fn connect(&self, port: MidiOutputPort) -> MidiOutputHandle {
+        let handle = MidiOutputHandle(self.next_handle.fetch_add(1, Ordering::Relaxed));
         self.sender
-            .send(Message::ConnectToPort(port))
+            .send(Message::ConnectToPort(handle, port))
             .expect("Failed to connect to port");
+        handle
+    }
+
+    /// Connect to the first port whose name contains `name`, in addition to any
+    /// existing connections.
+    ///
+    /// Returns `None` if there is no such port.
+    pub fn connect_by_name(&self, name: &str) -> Option<MidiOutputHandle> {
+        let (_, port) = self.ports.iter().find(|(n, _)| n.contains(name))?;
+        Some(self.connect(port.clone()))
     }
 
-    /// Disconnect from the current output port.
+    /// Disconnect from all output ports.
     pub fn disconnect(&self) {
         self.sender
             .send(Message::DisconnectFromPort)
             .expect("Failed to disconnect from port");
     }
 
-    /// Send a midi message.
+    /// Disconnect only the connection identified by `handle`.
+    pub fn disconnect_handle(&self, handle: MidiOutputHandle) {
+        self.sender
+            .send(Message::DisconnectHandle(handle))
+            .expect("Failed to disconnect from port");
+    }
+
+    /// Send a midi message to the connected ports selected by the
+    /// [routing rules](MidiOutput::set_routing), which is all of them by default.
+    ///
+    /// If no port is connected, a [`MidiOutputError::SendDisconnectedError`] is
+    /// logged and emitted, unless [`MidiOutputSettings::drop_when_disconnected`] is set.
     pub fn send(&self, msg: MidiMessage) {
         self.sender
             .send(Message::Midi(msg))
             .expect("Couldn't send MIDI message");
     }
 
+    /// Send a NoteOn message to all connected ports.
+    ///
+    /// `channel` is in `0..16`; `key` and `velocity` in `0..128`.
+    pub fn note_on(&self, channel: u8, key: u8, velocity: u8) {
+        self.send(MidiMessage::note_on(channel, key, velocity));
+    }
+
+    /// Send a NoteOff message to all connected ports.
+    ///
+    /// `channel` is in `0..16`; `key` and `velocity` in `0..128`.
+    pub fn note_off(&self, channel: u8, key: u8, velocity: u8) {
+        self.send(MidiMessage::note_off(channel, key, velocity));
+    }
+
+    /// Send a polyphonic aftertouch message to all connected ports.
+    ///
+    /// `channel` is in `0..16`; `key` and `pressure` in `0..128`.
+    pub fn aftertouch(&self, channel: u8, key: u8, pressure: u8) {
+        self.send(MidiMessage::aftertouch(channel, key, pressure));
+    }
+
+    /// Send a channel pressure message to all connected ports.
+    ///
+    /// `channel` is in `0..16`; `pressure` in `0..128`.
+    pub fn channel_pressure(&self, channel: u8, pressure: u8) {
+        self.send(MidiMessage::channel_pressure(channel, pressure));
+    }
+
+    /// Release all notes on all channels, e.g. to silence notes left hanging
+    /// by a lost NoteOff.
+    ///
+    /// Sends sustain pedal off and All Notes Off (CC 123) on every channel.
+    pub fn all_notes_off(&self) {
+        for channel in 0..16 {
+            self.send(MidiMessage::control_change(channel, 64, 0));
+            self.send(MidiMessage::control_change(channel, 123, 0));
+        }
+    }
+
+    /// Send a midi message only to the connection identified by `handle`.
+    pub fn send_to(&self, handle: MidiOutputHandle, msg: MidiMessage) {
+        self.sender
+            .send(Message::MidiTo(handle, msg))
+            .expect("Couldn't send MIDI message");
+    }
+
+    /// Send a System Exclusive message to all connected ports.
+    ///
+    /// `data` must be the complete message, including the leading `0xF0` and trailing `0xF7`.
+    pub fn send_sysex(&self, data: Vec<u8>) {
+        self.sender
+            .send(Message::SysEx(data))
+            .expect("Couldn't send MIDI message");
+    }
+
+    /// Send a universal Identity Request to all connected ports.
+    ///
+    /// Devices answer with a SysEx message that can be decoded with
+    /// [`DeviceIdentityResponse::parse`](crate::sysex::DeviceIdentityResponse::parse).
+    pub fn send_identity_request(&self) {
+        self.send_sysex(IDENTITY_REQUEST.to_vec());
+    }
+
+    /// Crossfade the messages sent with [`MidiOutput::send`] between the
+    /// connections `port_a` and `port_b`.
+    ///
+    /// With a `mix` of `0.0` NoteOn messages only go to `port_a`, with `1.0`
+    /// only to `port_b`, and in between to both, with their velocities scaled
+    /// by `1.0 - mix` and `mix` respectively. Other messages, including NoteOff,
+    /// go to both so that no notes are left hanging. Both connections must
+    /// also be selected by the [routing](MidiOutput::set_routing).
+    ///
+    /// Changing the mix of the current crossfade moves towards it at
+    /// [`MidiOutputSettings::crossfade_slew_rate`]; a new pair of connections
+    /// starts at `mix` right away.
+    pub fn set_crossfade(&self, port_a: MidiOutputHandle, port_b: MidiOutputHandle, mix: f32) {
+        self.sender
+            .send(Message::SetCrossfade(Some((port_a, port_b, mix))))
+            .expect("Couldn't set MIDI crossfade");
+    }
+
+    /// Stop crossfading, see [`MidiOutput::set_crossfade`].
+    pub fn clear_crossfade(&self) {
+        self.sender
+            .send(Message::SetCrossfade(None))
+            .expect("Couldn't set MIDI crossfade");
+    }
+
+    /// Replace the rules selecting which connections [`MidiOutput::send`] sends to.
+    ///
+    /// A message is sent to every connection selected by any of the rules; with
+    /// no rules, it is sent to all connections. Messages sent with
+    /// [`MidiOutput::send_to`] ignore the routing rules.
+    pub fn set_routing(&self, rules: Vec<RoutingRule>) {
+        self.routing
+            .lock()
+            .expect("Couldn't lock MIDI routing")
+            .clone_from(&rules);
+        self.sender
+            .send(Message::SetRouting(rules))
+            .expect("Couldn't set MIDI routing");
+    }
+
+    /// Get the current routing rules, see [`MidiOutput::set_routing`].
+    #[must_use]
+    pub fn routing(&self) -> Vec<RoutingRule> {
+        self.routing
+            .lock()
+            .expect("Couldn't lock MIDI routing")
+            .clone()
+    }
+
+    /// Queue a midi message to be sent at the given `tick`.
+    ///
+    /// The message is sent from [`PreUpdate`] during the first update whose
+    /// [`tick`](MidiOutput::tick) is at least `tick`. Messages for ticks that have
+    /// already passed are sent on the next update.
+    pub fn send_at_tick(&self, msg: MidiMessage, tick: u64) {
+        self.schedule_sender
+            .send((tick, msg))
+            .expect("Couldn't schedule MIDI message");
+    }
+
+    /// Get the current tick, which counts the updates since [`MidiOutputPlugin`] started.
+    #[must_use]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
     /// Get the current output ports, and their names.
     #[must_use]
     pub fn ports(&self) -> &Vec<(String, MidiOutputPort)> {
         &self.ports
     }
+
+    /// Get the number of current output ports.
+    #[must_use]
+    pub fn port_count(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Check whether there are any output ports.
+    #[must_use]
+    pub fn has_ports(&self) -> bool {
+        !self.ports.is_empty()
+    }
+
+    /// Get the current output ports as a multi-line string, one `"index: name"` per line.
+    #[must_use]
+    pub fn ports_display(&self) -> String {
+        self.ports
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| format!("{}: {}\n", i, name))
+            .collect()
+    }
 }
 
-/// [`Resource`](bevy::ecs::system::Resource) for checking whether [`MidiOutput`] is
-/// connected to any ports.
+/// A midi output port, as listed by [`MidiOutput::ports`].
+///
+/// The port's name is looked up when the ports are refreshed and stored
+/// alongside it.
 ///
-/// Change detection fires whenever the connection changes.
+/// The [`Default`] port is an invalid placeholder with an empty name, which fails
+/// with [`ConnectErrorKind::InvalidPort`] when connected to.
+#[derive(Clone, Default, PartialEq)]
+pub struct MidiOutputPort {
+    port: Option<midir::MidiOutputPort>,
+    name: Arc<str>,
+}
+
+impl MidiOutputPort {
+    /// Get the name of the port.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check whether this is a listed port, rather than the [`Default`] placeholder.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.port.is_some()
+    }
+}
+
+impl std::fmt::Debug for MidiOutputPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MidiOutputPort")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Identifies one of [`MidiOutput`]'s connections, as returned by [`MidiOutput::connect`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MidiOutputHandle(usize);
+
+/// A rule selecting which connections a message sent with [`MidiOutput::send`] goes to.
+///
+/// Connections are identified by their index in connection order, i.e. the
+/// oldest active connection has index 0.
+#[derive(Clone)]
+pub enum RoutingRule {
+    /// Send every message to all connections.
+    AllPorts,
+    /// Send every message to the connection at the given index.
+    PortByIndex(usize),
+    /// Send messages on the given channel, `0..16`, to the connection at the given index.
+    /// System messages never match.
+    ChannelToPort(u8, usize),
+    /// Send messages to the connections at the indices returned by the function.
+    CustomFn(Arc<RoutingFn>),
+}
+
+/// A function returning the indices of the connections a message should be sent to,
+/// see [`RoutingRule::CustomFn`].
+pub type RoutingFn = dyn Fn(&MidiMessage) -> Vec<usize> + Send + Sync;
+
+impl std::fmt::Debug for RoutingRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingRule::AllPorts => write!(f, "AllPorts"),
+            RoutingRule::PortByIndex(i) => f.debug_tuple("PortByIndex").field(i).finish(),
+            RoutingRule::ChannelToPort(channel, i) => f
+                .debug_tuple("ChannelToPort")
+                .field(channel)
+                .field(i)
+                .finish(),
+            RoutingRule::CustomFn(_) => f.debug_tuple("CustomFn").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl RoutingRule {
+    fn targets(&self, message: &MidiMessage, connections: usize, out: &mut Vec<usize>) {
+        match self {
+            RoutingRule::AllPorts => out.extend(0..connections),
+            RoutingRule::PortByIndex(i) => out.push(*i),
+            RoutingRule::ChannelToPort(channel, i) => {
+                if message.msg[0] < 0xF0 && message.channel() == *channel {
+                    out.push(*i);
+                }
+            }
+            RoutingRule::CustomFn(f) => out.extend(f(message)),
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) for checking which ports [`MidiOutput`]
+/// is connected to.
+///
+/// Change detection fires whenever a connection is made or lost, i.e. whenever
+/// [`MidiOutputConnection::connected_ports`] changes.
 #[derive(Resource, Default)]
 pub struct MidiOutputConnection {
-    connected: bool,
+    connections: Vec<(MidiOutputHandle, String)>,
 }
 
 impl MidiOutputConnection {
+    /// Check whether [`MidiOutput`] is connected to at least one port.
     #[must_use]
     pub fn is_connected(&self) -> bool {
-        self.connected
+        !self.connections.is_empty()
+    }
+
+    /// Get the number of ports [`MidiOutput`] is connected to.
+    #[must_use]
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Check whether the connection identified by `handle` is active.
+    #[must_use]
+    pub fn is_handle_connected(&self, handle: MidiOutputHandle) -> bool {
+        self.connections.iter().any(|(h, _)| *h == handle)
+    }
+
+    /// Get the names of the ports [`MidiOutput`] is connected to, in connection order.
+    #[must_use]
+    pub fn connected_ports(&self) -> Vec<String> {
+        self.connections
+            .iter()
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    /// Check whether [`MidiOutput`] is connected to a port whose name contains `name`.
+    #[must_use]
+    pub fn is_connected_to(&self, name: &str) -> bool {
+        self.connections.iter().any(|(_, n)| n.contains(name))
+    }
+}
+
+/// A run condition that is `true` while [`MidiOutput`] is connected to a port
+/// whose name contains `port_name`, see [`MidiOutputConnection::is_connected_to`].
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_midi::prelude::*;
+/// # fn send_to_synth() {}
+/// # let mut app = App::new();
+/// app.add_systems(Update, send_to_synth.run_if(midi_output_connected_to("Synth")));
+/// ```
+pub fn midi_output_connected_to(
+    port_name: impl Into<String>,
+) -> impl FnMut(Option<Res<MidiOutputConnection>>) -> bool + Clone {
+    let port_name = port_name.into();
+    move |connection: Option<Res<MidiOutputConnection>>| {
+        connection.is_some_and(|c| c.is_connected_to(&port_name))
+    }
+}
+
+/// [`Command`] sending a midi message with [`MidiOutput::send`] when the
+/// commands are applied.
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_midi::prelude::*;
+/// fn play(mut commands: Commands) {
+///     commands.add(SendMidiCommand(MidiMessage::note_on(0, midi_note!(C 4), 100)));
+/// }
+/// ```
+///
+/// Logs a warning instead if [`MidiOutputPlugin`] wasn't added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendMidiCommand(pub MidiMessage);
+
+impl Command for SendMidiCommand {
+    fn apply(self, world: &mut World) {
+        match world.get_resource::<MidiOutput>() {
+            Some(output) => output.send(self.0),
+            None => warn!("Couldn't send {:?}: MidiOutputPlugin wasn't added", self.0),
+        }
+    }
+}
+
+/// [`Component`] calling [`MidiOutput::all_notes_off`] when `key` is pressed.
+///
+/// Only one entity needs it; the notes are released once per key press no matter
+/// how many entities have it.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidiPanicButton {
+    pub key: KeyCode,
+}
+
+impl Default for MidiPanicButton {
+    fn default() -> Self {
+        MidiPanicButton { key: KeyCode::F12 }
+    }
+}
+
+fn panic_button(
+    buttons: Query<&MidiPanicButton>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    output: Res<MidiOutput>,
+) {
+    let Some(keys) = keys else {
+        return;
+    };
+    if buttons.iter().any(|b| keys.just_pressed(b.key)) {
+        output.all_notes_off();
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the last control change and
+/// program change values sent, per channel, enabled by
+/// [`MidiOutputSettings::restore_state_on_reconnect`].
+///
+/// Updated from [`PreUpdate`]; values that were never sent are `None`. Channel
+/// mode messages (controllers 120 to 127, e.g. All Notes Off) aren't tracked,
+/// since replaying them would reset the restored state or switch the device's mode.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiOutputState {
+    cc: [[Option<u8>; 128]; 16],
+    programs: [Option<u8>; 16],
+}
+
+// Controllers above this are channel mode messages
+const LAST_TRACKED_CC: u8 = 119;
+
+impl Default for MidiOutputState {
+    fn default() -> Self {
+        MidiOutputState {
+            cc: [[None; 128]; 16],
+            programs: [None; 16],
+        }
     }
 }
 
-/// The [`Error`] type for midi output operations, accessible as an [`Event`](bevy::ecs::event::Event)
+impl MidiOutputState {
+    /// Get the last value sent for controller `cc` on `channel`.
+    #[must_use]
+    pub fn cc(&self, channel: u8, cc: u8) -> Option<u8> {
+        self.cc[usize::from(channel & 0x0F)][usize::from(cc & 0x7F)]
+    }
+
+    /// Get the last program sent on `channel`.
+    #[must_use]
+    pub fn program(&self, channel: u8) -> Option<u8> {
+        self.programs[usize::from(channel & 0x0F)]
+    }
+
+    // Returns whether `message` is tracked
+    fn record(&mut self, message: &MidiMessage) -> bool {
+        let [status, a, b] = message.msg;
+        let channel = usize::from(message.channel());
+        match status & 0b1111_0000 {
+            CONTROL_CHANGE_STATUS if a <= LAST_TRACKED_CC => {
+                self.cc[channel][usize::from(a)] = Some(b);
+            }
+            PROGRAM_CHANGE_STATUS => self.programs[channel] = Some(a),
+            _ => return false,
+        }
+        true
+    }
+
+    // The messages restoring this state, program changes first since they may
+    // reset controllers
+    fn messages(&self) -> Vec<MidiMessage> {
+        let mut messages = Vec::new();
+        for (channel, program) in (0u8..).zip(self.programs) {
+            if let Some(program) = program {
+                messages.push([PROGRAM_CHANGE_STATUS | channel, program].into());
+            }
+        }
+        for (channel, values) in (0u8..).zip(&self.cc) {
+            for (cc, value) in (0u8..).zip(values) {
+                if let Some(value) = value {
+                    messages.push(MidiMessage::control_change(channel, cc, *value));
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for a midi message that was sent, enabled by
+/// [`MidiOutputSettings::echo_to_input`].
+///
+/// The stamp is in microseconds since [`MidiOutputPlugin`] started. This event
+/// fires from [`PreUpdate`], once per message even if it was sent to several ports.
 #[derive(Clone, Debug, Event)]
+pub struct SentMidiData(pub MidiData);
+
+/// The [`Error`](std::error::Error) type for midi output operations, accessible as an [`Event`](bevy::ecs::event::Event)
+#[derive(Clone, Debug, Event, thiserror::Error)]
 pub enum MidiOutputError {
+    #[error("Couldn't (re)connect to output port: {0}")]
     ConnectionError(ConnectErrorKind),
-    SendError(midir::SendError),
+    #[error("{0}")]
+    SendError(#[from] midir::SendError),
+    #[error("Couldn't send midi message {0}; output is disconnected")]
     SendDisconnectedError(MidiMessage),
+    #[error("Couldn't refresh output ports")]
     PortRefreshError,
 }
 
-impl Error for MidiOutputError {}
-impl Display for MidiOutputError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+impl MidiOutputError {
+    /// Get the reason a connection failed, if this is a [`MidiOutputError::ConnectionError`].
+    #[must_use]
+    pub fn connection_failure_reason(&self) -> Option<ConnectionFailureReason> {
         match self {
-            SendError(e) => e.fmt(f)?,
-            SendDisconnectedError(m) => write!(
-                f,
-                "Couldn't send midi message {:?}; output is disconnected",
-                m
-            )?,
-            ConnectionError(k) => match k {
-                ConnectErrorKind::InvalidPort => {
-                    write!(f, "Couldn't (re)connect to output port: invalid port")?;
-                }
-                ConnectErrorKind::Other(s) => {
-                    write!(f, "Couldn't (re)connect to output port: {}", s)?;
-                }
-            },
-            PortRefreshError => write!(f, "Couldn't refresh output ports")?,
+            ConnectionError(kind) => Some(kind.into()),
+            _ => None,
         }
-        Ok(())
     }
 }
 
@@ -135,28 +619,74 @@ fn setup(mut commands: Commands, settings: Res<MidiOutputSettings>) {
     let (m_sender, m_receiver) = crossbeam_channel::unbounded();
     let (r_sender, r_receiver) = crossbeam_channel::unbounded();
 
-    let thread_pool = IoTaskPool::get();
+    // List the ports right away, so they're available from the first update
+    let output = midir::MidiOutput::new(&settings.port_name).ok();
+    if let Some(output) = &output {
+        r_sender.send(get_available_ports(output)).unwrap();
+    }
+
+    let thread_pool = io_task_pool();
     thread_pool
         .spawn(MidiOutputTask {
             receiver: m_receiver,
             sender: r_sender,
             settings: settings.clone(),
-            output: None,
-            connection: None,
+            start: Instant::now(),
+            routing: Vec::new(),
+            crossfade: None,
+            state: MidiOutputState::default(),
+            active_notes: Default::default(),
+            output,
+            connections: Vec::new(),
         })
         .detach();
 
+    let (schedule_sender, schedule_receiver) = crossbeam_channel::unbounded();
     commands.insert_resource(MidiOutput {
         sender: m_sender,
         receiver: r_receiver,
         ports: Vec::new(),
+        next_handle: AtomicUsize::new(0),
+        tick: 0,
+        schedule_sender,
+        schedule_receiver,
+        scheduled: Vec::new(),
+        routing: Mutex::new(Vec::new()),
     });
 }
 
+fn send_scheduled(mut output: ResMut<MidiOutput>) {
+    // Advancing the tick shouldn't fire change detection
+    let output = output.bypass_change_detection();
+    output.tick += 1;
+    while let Ok(scheduled) = output.schedule_receiver.try_recv() {
+        output.scheduled.push(scheduled);
+    }
+
+    let tick = output.tick;
+    let mut due: Vec<_> = output
+        .scheduled
+        .iter()
+        .filter(|(t, _)| *t <= tick)
+        .copied()
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+    output.scheduled.retain(|(t, _)| *t > tick);
+
+    due.sort_by_key(|(t, _)| *t);
+    for (_, msg) in due {
+        output.send(msg);
+    }
+}
+
 fn reply(
     mut output: ResMut<MidiOutput>,
     mut conn: ResMut<MidiOutputConnection>,
     mut err: EventWriter<MidiOutputError>,
+    mut sent: EventWriter<SentMidiData>,
+    mut state: ResMut<MidiOutputState>,
 ) {
     while let Ok(msg) = output.receiver.try_recv() {
         match msg {
@@ -167,38 +697,194 @@ fn reply(
                 warn!("{}", e);
                 err.send(e);
             }
-            Reply::Connected => {
-                conn.connected = true;
+            Reply::Connected(handle, name) => {
+                conn.connections.push((handle, name));
+            }
+            Reply::Disconnected(handle) => {
+                conn.connections.retain(|(h, _)| *h != handle);
             }
-            Reply::Disconnected => {
-                conn.connected = false;
+            Reply::Sent(data) => {
+                sent.send(SentMidiData(data));
+            }
+            Reply::StateChanged(message) => {
+                state.record(&message);
             }
         }
     }
 }
 
-enum Message {
+/// Requests sent from [`MidiOutput`] to the background task.
+///
+/// This is crate-private for now, but is intended to become public so that
+/// custom output backends can be implemented; it is `#[non_exhaustive]` so that
+/// variants can keep being added once it is.
+#[non_exhaustive]
+pub(crate) enum Message {
     RefreshPorts,
-    ConnectToPort(MidiOutputPort),
+    ConnectToPort(MidiOutputHandle, MidiOutputPort),
     DisconnectFromPort,
+    DisconnectHandle(MidiOutputHandle),
     Midi(MidiMessage),
+    MidiTo(MidiOutputHandle, MidiMessage),
+    SysEx(Vec<u8>),
+    SetRouting(Vec<RoutingRule>),
+    SetCrossfade(Option<(MidiOutputHandle, MidiOutputHandle, f32)>),
 }
 
-enum Reply {
+/// Replies sent from the background task back to [`MidiOutput`].
+///
+/// See [`Message`] for why this is crate-private.
+#[non_exhaustive]
+pub(crate) enum Reply {
     AvailablePorts(Vec<(String, MidiOutputPort)>),
     Error(MidiOutputError),
-    Connected,
-    Disconnected,
+    Connected(MidiOutputHandle, String),
+    Disconnected(MidiOutputHandle),
+    Sent(MidiData),
+    StateChanged(MidiMessage),
 }
 
 struct MidiOutputTask {
     receiver: Receiver<Message>,
     sender: Sender<Reply>,
     settings: MidiOutputSettings,
+    start: Instant,
+    routing: Vec<RoutingRule>,
+    crossfade: Option<Crossfade>,
+    state: MidiOutputState,
+    // Keys playing on each channel, oldest first, see `steal_voice`
+    active_notes: [Vec<u8>; 16],
 
-    // Invariant: exactly one of `output` or `connection` is Some
+    // Only used to list ports; every connection owns its own midir::MidiOutput
     output: Option<midir::MidiOutput>,
-    connection: Option<(midir::MidiOutputConnection, MidiOutputPort)>,
+    connections: Vec<(
+        MidiOutputHandle,
+        midir::MidiOutputConnection,
+        MidiOutputPort,
+    )>,
+}
+
+// Two connections crossfaded by `MidiOutput::set_crossfade`
+struct Crossfade {
+    a: MidiOutputHandle,
+    b: MidiOutputHandle,
+    mix: f32,
+    target: f32,
+    updated: Instant,
+}
+
+impl Crossfade {
+    // Move `mix` towards `target`, by at most `slew_rate` per second since the last update
+    fn advance(&mut self, slew_rate: f32) {
+        let now = Instant::now();
+        if slew_rate.is_finite() && slew_rate > 0.0 {
+            let step = slew_rate * now.duration_since(self.updated).as_secs_f32();
+            self.mix += (self.target - self.mix).clamp(-step, step);
+        } else {
+            self.mix = self.target;
+        }
+        self.updated = now;
+    }
+
+    fn gain(&self, handle: MidiOutputHandle) -> Option<f32> {
+        if handle == self.a {
+            Some(1.0 - self.mix)
+        } else if handle == self.b {
+            Some(self.mix)
+        } else {
+            None
+        }
+    }
+}
+
+impl MidiOutputTask {
+    fn close(&mut self, index: usize) {
+        let (handle, conn, _) = self.connections.remove(index);
+        conn.close();
+        self.sender.send(Reply::Disconnected(handle)).unwrap();
+    }
+
+    // Keeps track of the notes playing on each channel, and returns the NoteOff
+    // for the oldest one if `message` would exceed the polyphony limit
+    fn steal_voice(&mut self, message: &MidiMessage) -> Option<MidiMessage> {
+        let max = usize::from(self.settings.max_polyphony_per_channel?.max(1));
+        let channel = message.channel();
+        let [_, key, velocity] = message.msg;
+        let active = &mut self.active_notes[usize::from(channel)];
+        if message.is_note_on() || message.is_note_off() {
+            active.retain(|k| *k != key);
+        } else if message.is_control_change() && matches!(key, 120 | 123) {
+            // All Sound Off and All Notes Off
+            active.clear();
+        }
+        if !message.is_note_on() || velocity == 0 {
+            return None;
+        }
+        let stolen = (active.len() >= max).then(|| active.remove(0));
+        active.push(key);
+        stolen.map(|key| MidiMessage::note_off(channel, key, 0))
+    }
+
+    fn track(&mut self, message: &MidiMessage) {
+        if self.settings.restore_state_on_reconnect && self.state.record(message) {
+            self.sender.send(Reply::StateChanged(*message)).unwrap();
+        }
+    }
+
+    fn restore_state(&mut self, index: usize) {
+        if self.settings.restore_state_on_reconnect {
+            for message in self.state.messages() {
+                self.send_to(index, message.as_bytes());
+            }
+        }
+    }
+
+    fn echo(&self, message: MidiMessage) {
+        if self.settings.echo_to_input {
+            let stamp = self.start.elapsed().as_micros() as u64;
+            self.sender
+                .send(Reply::Sent(MidiData { stamp, message }))
+                .unwrap();
+        }
+    }
+
+    // Indices of the connections `message` should be sent to, without duplicates
+    fn route(&self, message: &MidiMessage) -> Vec<usize> {
+        let len = self.connections.len();
+        if self.routing.is_empty() {
+            return (0..len).collect();
+        }
+        let mut targets = Vec::new();
+        for rule in &self.routing {
+            rule.targets(message, len, &mut targets);
+        }
+        targets.retain(|i| *i < len);
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    // `message` as sent to connection `index` with the crossfade applied, or
+    // None for a NoteOn that is silent on that connection
+    fn crossfaded(&self, index: usize, mut message: MidiMessage) -> Option<MidiMessage> {
+        let handle = self.connections[index].0;
+        let gain = self.crossfade.as_ref().and_then(|c| c.gain(handle));
+        let velocity = message.msg[2];
+        match gain {
+            Some(gain) if message.is_note_on() && velocity > 0 => {
+                let scaled = (f32::from(velocity) * gain).round() as u8;
+                message.msg[2] = scaled.min(127);
+                (scaled > 0).then_some(message)
+            }
+            _ => Some(message),
+        }
+    }
+
+    fn send_to(&mut self, index: usize, message: &[u8]) {
+        if let Err(e) = self.connections[index].1.send(message) {
+            self.sender.send(Reply::Error(SendError(e))).unwrap();
+        }
+    }
 }
 
 impl Future for MidiOutputTask {
@@ -208,84 +894,164 @@ impl Future for MidiOutputTask {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        if self.output.is_none() && self.connection.is_none() {
-            self.output = midir::MidiOutput::new(self.settings.port_name).ok();
+        if self.output.is_none() {
+            self.output = midir::MidiOutput::new(&self.settings.port_name).ok();
             self.sender
                 .send(get_available_ports(self.output.as_ref().unwrap()))
                 .unwrap();
         }
 
         if let Ok(msg) = self.receiver.recv() {
-            use Message::{ConnectToPort, DisconnectFromPort, Midi, RefreshPorts};
+            use Message::{
+                ConnectToPort, DisconnectFromPort, DisconnectHandle, Midi, MidiTo, RefreshPorts,
+                SetCrossfade, SetRouting, SysEx,
+            };
 
             match msg {
-                ConnectToPort(port) => {
-                    let was_connected = self.output.is_none();
-                    let out = self
-                        .output
-                        .take()
-                        .unwrap_or_else(|| self.connection.take().unwrap().0.close());
-                    match out.connect(&port, self.settings.port_name) {
+                ConnectToPort(handle, port) => {
+                    if let Some(i) = self.connections.iter().position(|(_, _, p)| *p == port) {
+                        self.close(i);
+                    }
+                    let conn = midir::MidiOutput::new(&self.settings.port_name)
+                        .map_err(|_| ConnectErrorKind::Other("couldn't create midi output"))
+                        .and_then(|out| match &port.port {
+                            Some(p) => out
+                                .connect(p, &self.settings.port_name)
+                                .map_err(|e| e.kind()),
+                            None => Err(ConnectErrorKind::InvalidPort),
+                        });
+                    match conn {
                         Ok(conn) => {
-                            self.connection = Some((conn, port));
-                            self.output = None;
-                            self.sender.send(Reply::Connected).unwrap();
+                            self.sender
+                                .send(Reply::Connected(handle, port.name().to_string()))
+                                .unwrap();
+                            self.connections.push((handle, conn, port));
+                            let index = self.connections.len() - 1;
+                            self.restore_state(index);
                         }
-                        Err(conn_err) => {
+                        Err(kind) => {
                             self.sender
-                                .send(Reply::Error(ConnectionError(conn_err.kind())))
+                                .send(Reply::Error(ConnectionError(kind)))
                                 .unwrap();
-                            if was_connected {
-                                self.sender.send(Reply::Disconnected).unwrap();
-                            }
-                            self.connection = None;
-                            self.output = Some(conn_err.into_inner());
                         }
                     }
                 }
                 DisconnectFromPort => {
-                    if let Some((conn, _)) = self.connection.take() {
-                        self.output = Some(conn.close());
-                        self.connection = None;
-                        self.sender.send(Reply::Disconnected).unwrap();
+                    while !self.connections.is_empty() {
+                        self.close(0);
                     }
                 }
-                RefreshPorts => match &self.output {
-                    Some(out) => {
-                        self.sender.send(get_available_ports(out)).unwrap();
+                DisconnectHandle(handle) => {
+                    if let Some(i) = self.connections.iter().position(|(h, _, _)| *h == handle) {
+                        self.close(i);
                     }
-                    None => {
-                        let (conn, port) = self.connection.take().unwrap();
-                        let out = conn.close();
-
-                        self.sender.send(get_available_ports(&out)).unwrap();
-
-                        match out.connect(&port, self.settings.port_name) {
-                            Ok(conn) => {
-                                self.connection = Some((conn, port));
-                                self.output = None;
-                            }
-                            Err(conn_err) => {
+                }
+                RefreshPorts => {
+                    let ports = get_available_ports(self.output.as_ref().unwrap());
+                    if let Reply::AvailablePorts(ports) = &ports {
+                        // Drop connections to ports that have disappeared
+                        let mut i = 0;
+                        while i < self.connections.len() {
+                            if ports.iter().any(|(_, p)| *p == self.connections[i].2) {
+                                i += 1;
+                            } else {
                                 self.sender
-                                    .send(Reply::Error(ConnectionError(conn_err.kind())))
+                                    .send(Reply::Error(ConnectionError(
+                                        ConnectErrorKind::InvalidPort,
+                                    )))
                                     .unwrap();
-                                self.sender.send(Reply::Disconnected).unwrap();
-                                self.connection = None;
-                                self.output = Some(conn_err.into_inner());
+                                self.close(i);
                             }
                         }
                     }
-                },
+                    self.sender.send(ports).unwrap();
+                }
                 Midi(message) => {
-                    if let Some((conn, _)) = &mut self.connection {
-                        if let Err(e) = conn.send(&message.msg) {
-                            self.sender.send(Reply::Error(SendError(e))).unwrap();
-                        }
-                    } else {
+                    if self.connections.is_empty() && !self.settings.drop_when_disconnected {
                         self.sender
                             .send(Reply::Error(SendDisconnectedError(message)))
                             .unwrap();
                     }
+                    self.track(&message);
+                    if let Some(stolen) = self.steal_voice(&message) {
+                        let targets = self.route(&stolen);
+                        for &i in &targets {
+                            self.send_to(i, stolen.as_bytes());
+                        }
+                        if !targets.is_empty() {
+                            self.echo(stolen);
+                        }
+                    }
+                    let targets = self.route(&message);
+                    let slew_rate = self.settings.crossfade_slew_rate;
+                    if let Some(crossfade) = &mut self.crossfade {
+                        crossfade.advance(slew_rate);
+                    }
+                    for &i in &targets {
+                        if let Some(message) = self.crossfaded(i, message) {
+                            self.send_to(i, message.as_bytes());
+                        }
+                    }
+                    if !targets.is_empty() {
+                        self.echo(message);
+                    }
+                }
+                SetRouting(rules) => {
+                    self.routing = rules;
+                }
+                SetCrossfade(Some((a, b, mix))) => {
+                    let mix = mix.clamp(0.0, 1.0);
+                    let slew_rate = self.settings.crossfade_slew_rate;
+                    match &mut self.crossfade {
+                        Some(crossfade) if crossfade.a == a && crossfade.b == b => {
+                            crossfade.advance(slew_rate);
+                            crossfade.target = mix;
+                        }
+                        _ => {
+                            self.crossfade = Some(Crossfade {
+                                a,
+                                b,
+                                mix,
+                                target: mix,
+                                updated: Instant::now(),
+                            });
+                        }
+                    }
+                }
+                SetCrossfade(None) => {
+                    self.crossfade = None;
+                }
+                SysEx(data) => {
+                    if self.connections.is_empty() && !self.settings.drop_when_disconnected {
+                        self.sender
+                            .send(Reply::Error(SendError(midir::SendError::Other(
+                                "couldn't send sysex message; output is disconnected",
+                            ))))
+                            .unwrap();
+                    }
+                    for i in 0..self.connections.len() {
+                        self.send_to(i, &data);
+                    }
+                }
+                MidiTo(handle, message) => {
+                    self.track(&message);
+                    let stolen = self.steal_voice(&message);
+                    match self.connections.iter().position(|(h, _, _)| *h == handle) {
+                        Some(i) => {
+                            if let Some(stolen) = stolen {
+                                self.send_to(i, stolen.as_bytes());
+                                self.echo(stolen);
+                            }
+                            self.send_to(i, message.as_bytes());
+                            self.echo(message);
+                        }
+                        None if self.settings.drop_when_disconnected => {}
+                        None => {
+                            self.sender
+                                .send(Reply::Error(SendDisconnectedError(message)))
+                                .unwrap();
+                        }
+                    }
                 }
             }
         }
@@ -305,7 +1071,15 @@ fn get_available_ports(output: &midir::MidiOutput) -> Reply {
         let ports = output.ports();
         let ports: Result<Vec<_>, _> = ports
             .into_iter()
-            .map(|p| output.port_name(&p).map(|n| (n, p)))
+            .map(|p| {
+                output.port_name(&p).map(|n| {
+                    let port = MidiOutputPort {
+                        port: Some(p),
+                        name: n.as_str().into(),
+                    };
+                    (n, port)
+                })
+            })
             .collect();
         if let Ok(ports) = ports {
             return Reply::AvailablePorts(ports);
@@ -313,3 +1087,37 @@ fn get_available_ports(output: &midir::MidiOutput) -> Reply {
     }
     Reply::Error(PortRefreshError)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossfade_without_positive_slew_rate_jumps() {
+        for slew_rate in [0.0, -1.0, f32::INFINITY, f32::NAN] {
+            let mut crossfade = Crossfade {
+                a: MidiOutputHandle(0),
+                b: MidiOutputHandle(1),
+                mix: 0.0,
+                target: 1.0,
+                updated: Instant::now(),
+            };
+            crossfade.advance(slew_rate);
+            assert_eq!(crossfade.mix, 1.0);
+        }
+    }
+
+    #[test]
+    fn output_state_skips_channel_mode_messages() {
+        let mut state = MidiOutputState::default();
+        assert!(state.record(&MidiMessage::control_change(0, 7, 100)));
+        assert!(!state.record(&MidiMessage::control_change(0, 121, 0)));
+        assert!(!state.record(&MidiMessage::control_change(0, 123, 0)));
+        assert_eq!(state.cc(0, 7), Some(100));
+        assert_eq!(state.cc(0, 121), None);
+        assert_eq!(
+            state.messages(),
+            vec![MidiMessage::control_change(0, 7, 100)]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use super::output::MidiOutput;
+use super::MidiMessage;
+use bevy::prelude::*;
+use nodi::midly::{Format, MidiMessage as SmfMessage, Smf, Timing};
+use nodi::{Event, Sheet};
+use std::time::Duration;
+
+/// Tempo of a standard midi file until its first tempo event, in microseconds per beat (120 bpm).
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// [`Resource`](bevy::ecs::system::Resource) playing a standard midi file on [`MidiOutput`].
+///
+/// While this resource is present, with [`MidiOutputPlugin`](crate::output::MidiOutputPlugin)
+/// added, the file advances with the app's [`Time`] whenever [`play`](Self::play) was called.
+/// Tempo changes in the file are followed.
+///
+/// This plays a parsed [`nodi::Sheet`] from a system instead of running a
+/// [`nodi::Player`], which blocks its thread until the file ends and couldn't be
+/// paused or sought from the app.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiPlayer {
+    sheet: Sheet,
+    ticks_per_beat: u16,
+    // Microseconds per beat at the current position
+    tempo: u32,
+    // Index of the next moment to play, one moment per tick
+    position: usize,
+    // Microseconds until the next moment is due
+    wait: f64,
+    playing: bool,
+    // Channel and key of the notes playing
+    sounding: Vec<(u8, u8)>,
+    // Whether the sounding notes should be released on the next update
+    release: bool,
+}
+
+impl MidiPlayer {
+    /// Create a stopped player for a sheet with `ticks_per_beat` ticks per beat.
+    #[must_use]
+    pub fn new(sheet: Sheet, ticks_per_beat: u16) -> Self {
+        MidiPlayer {
+            sheet,
+            ticks_per_beat: ticks_per_beat.max(1),
+            tempo: DEFAULT_TEMPO,
+            position: 0,
+            wait: 0.0,
+            playing: false,
+            sounding: Vec::new(),
+            release: false,
+        }
+    }
+
+    /// Parse a standard midi file into a stopped player.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be parsed, or if it's timed in timecode instead of beats.
+    pub fn from_smf(bytes: &[u8]) -> Result<Self, MidiPlaybackError> {
+        let smf = Smf::parse(bytes)?;
+        let Timing::Metrical(ticks_per_beat) = smf.header.timing else {
+            return Err(MidiPlaybackError::UnsupportedTiming);
+        };
+        let sheet = match smf.header.format {
+            Format::SingleTrack => smf
+                .tracks
+                .first()
+                .map(|track| Sheet::single(track))
+                .unwrap_or_default(),
+            Format::Parallel => Sheet::parallel(&smf.tracks),
+            Format::Sequential => Sheet::sequential(&smf.tracks),
+        };
+        Ok(MidiPlayer::new(sheet, ticks_per_beat.as_int()))
+    }
+
+    /// Start or resume playing from the current position.
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Stop playing, keeping the current position. Notes that are playing are released.
+    pub fn pause(&mut self) {
+        self.playing = false;
+        self.release = true;
+    }
+
+    /// Stop playing and go back to the start. Notes that are playing are released.
+    pub fn stop(&mut self) {
+        self.pause();
+        self.seek_to_beat(0.0);
+    }
+
+    /// Move to `beat` beats from the start, clamped to the end of the file.
+    ///
+    /// The tempo is set to the one in effect at that position. Notes that are
+    /// playing are released; notes started before `beat` aren't replayed.
+    pub fn seek_to_beat(&mut self, beat: f32) {
+        let tick = (beat.max(0.0) * f32::from(self.ticks_per_beat)).round() as usize;
+        self.position = tick.min(self.sheet.len());
+        self.tempo = self.sheet[..self.position]
+            .iter()
+            .flat_map(|moment| &moment.events)
+            .rev()
+            .find_map(|event| match event {
+                Event::Tempo(tempo) => Some(*tempo),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_TEMPO);
+        self.wait = 0.0;
+        self.release = true;
+    }
+
+    #[must_use]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Whether the position is past the last event of the file.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.sheet.len()
+    }
+
+    /// The current position, in beats from the start.
+    #[must_use]
+    pub fn position_beats(&self) -> f32 {
+        self.position as f32 / f32::from(self.ticks_per_beat)
+    }
+
+    fn advance(&mut self, delta: Duration, mut send: impl FnMut(MidiMessage)) {
+        if self.release {
+            self.release = false;
+            for (channel, key) in self.sounding.drain(..) {
+                send(MidiMessage::note_off(channel, key, 0));
+            }
+        }
+        if !self.playing {
+            return;
+        }
+        self.wait -= delta.as_secs_f64() * 1_000_000.0;
+        while self.wait <= 0.0 {
+            let Some(moment) = self.sheet.get(self.position) else {
+                self.pause();
+                return;
+            };
+            for event in &moment.events {
+                match event {
+                    Event::Tempo(tempo) => self.tempo = *tempo,
+                    Event::Midi(event) => {
+                        let channel = event.channel.as_int();
+                        match event.message {
+                            SmfMessage::NoteOn { key, vel } if vel > 0 => {
+                                self.sounding.push((channel, key.as_int()));
+                            }
+                            SmfMessage::NoteOn { key, .. } | SmfMessage::NoteOff { key, .. } => {
+                                self.sounding
+                                    .retain(|sounding| *sounding != (channel, key.as_int()));
+                            }
+                            _ => {}
+                        }
+                        let mut bytes = Vec::with_capacity(3);
+                        if event.write(&mut bytes).is_ok() {
+                            let mut msg = [0; 3];
+                            let len = bytes.len().min(3);
+                            msg[..len].copy_from_slice(&bytes[..len]);
+                            send(MidiMessage::from(msg));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.position += 1;
+            self.wait += f64::from(self.tempo) / f64::from(self.ticks_per_beat);
+        }
+    }
+}
+
+/// The [`Error`](std::error::Error) type for loading a [`MidiPlayer`].
+#[derive(Debug, thiserror::Error)]
+pub enum MidiPlaybackError {
+    #[error("Couldn't parse midi file: {0}")]
+    ParseError(#[from] nodi::midly::Error),
+    #[error("Midi files timed in timecode aren't supported")]
+    UnsupportedTiming,
+}
+
+pub(crate) fn run_player(
+    mut player: ResMut<MidiPlayer>,
+    time: Res<Time>,
+    output: Option<Res<MidiOutput>>,
+) {
+    let Some(output) = output else {
+        return;
+    };
+    player.advance(time.delta(), |msg| output.send(msg));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nodi::midly::num::{u4, u7};
+    use nodi::{MidiEvent, Moment};
+
+    fn note(key: u8, vel: u8) -> Event {
+        let message = if vel > 0 {
+            SmfMessage::NoteOn {
+                key: u7::new(key),
+                vel: u7::new(vel),
+            }
+        } else {
+            SmfMessage::NoteOff {
+                key: u7::new(key),
+                vel: u7::new(0),
+            }
+        };
+        Event::Midi(MidiEvent {
+            channel: u4::new(0),
+            message,
+        })
+    }
+
+    // Two beats of 4 ticks: a note on beat 0, released on beat 1, and a tempo change on beat 1
+    fn player() -> MidiPlayer {
+        let mut moments = vec![Moment::default(); 8];
+        moments[0].events.push(note(60, 100));
+        moments[4].events.push(note(60, 0));
+        moments[4].events.push(Event::Tempo(250_000));
+        MidiPlayer::new(moments.into_iter().collect(), 4)
+    }
+
+    fn advance(player: &mut MidiPlayer, millis: u64) -> Vec<MidiMessage> {
+        let mut sent = Vec::new();
+        player.advance(Duration::from_millis(millis), |msg| sent.push(msg));
+        sent
+    }
+
+    #[test]
+    fn plays_only_after_play() {
+        let mut player = player();
+        assert!(advance(&mut player, 1000).is_empty());
+        player.play();
+        assert_eq!(
+            advance(&mut player, 0),
+            vec![MidiMessage::note_on(0, 60, 100)]
+        );
+        assert!(advance(&mut player, 400).is_empty());
+        assert_eq!(
+            advance(&mut player, 100),
+            vec![MidiMessage::note_off(0, 60, 0)]
+        );
+        assert_eq!(player.position_beats(), 1.25);
+    }
+
+    #[test]
+    fn follows_tempo_changes() {
+        let mut player = player();
+        player.seek_to_beat(1.0);
+        assert_eq!(player.tempo, DEFAULT_TEMPO);
+        player.seek_to_beat(1.25);
+        assert_eq!(player.tempo, 250_000);
+        player.play();
+        advance(&mut player, 0);
+        // Three ticks of 62.5ms play the rest of the file
+        advance(&mut player, 250);
+        assert!(player.is_finished());
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn pause_and_stop_release_notes() {
+        let mut player = player();
+        player.play();
+        advance(&mut player, 0);
+        player.pause();
+        assert_eq!(
+            advance(&mut player, 1000),
+            vec![MidiMessage::note_off(0, 60, 0)]
+        );
+        assert_eq!(player.position_beats(), 0.25);
+
+        player.play();
+        advance(&mut player, 1000);
+        player.stop();
+        assert!(advance(&mut player, 0).is_empty());
+        assert_eq!(player.position_beats(), 0.0);
+        assert_eq!(player.tempo, DEFAULT_TEMPO);
+    }
+
+    #[test]
+    fn seek_is_clamped_to_the_end() {
+        let mut player = player();
+        player.seek_to_beat(10.0);
+        assert!(player.is_finished());
+        player.seek_to_beat(-1.0);
+        assert_eq!(player.position_beats(), 0.0);
+    }
+}
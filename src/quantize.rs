@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+/// A note length used as the grid of a [`NoteQuantizer`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum NoteDivision {
+    Quarter,
+    Eighth,
+    #[default]
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl NoteDivision {
+    /// Number of grid lines per quarter note.
+    #[must_use]
+    pub fn per_quarter(self) -> u32 {
+        match self {
+            NoteDivision::Quarter => 1,
+            NoteDivision::Eighth => 2,
+            NoteDivision::Sixteenth => 4,
+            NoteDivision::ThirtySecond => 8,
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) that snaps the stamp of incoming NoteOn and
+/// NoteOff [`MidiData`](crate::input::MidiData) to the nearest line of a musical grid.
+///
+/// Other messages keep their stamps, so that the tempo of an incoming
+/// [`MidiClock`](crate::clock::MidiClock) is still measured from its actual timing.
+/// The grid starts at stamp `0`, i.e. when the input port was connected.
+/// Quantization is only applied while this resource is present.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct NoteQuantizer {
+    /// Tempo in quarter notes per minute.
+    pub bpm: f32,
+    pub subdivision: NoteDivision,
+}
+
+impl Default for NoteQuantizer {
+    fn default() -> Self {
+        NoteQuantizer {
+            bpm: 120.0,
+            subdivision: NoteDivision::default(),
+        }
+    }
+}
+
+impl NoteQuantizer {
+    /// Get the distance between two grid lines, in microseconds.
+    #[must_use]
+    pub fn grid_interval(&self) -> u64 {
+        (60_000_000.0 / (f64::from(self.bpm) * f64::from(self.subdivision.per_quarter()))) as u64
+    }
+
+    /// Snap `stamp` (in microseconds) to the nearest grid line.
+    ///
+    /// Stamps are returned unchanged if `bpm` is not positive.
+    #[must_use]
+    pub fn quantize(&self, stamp: u64) -> u64 {
+        let interval = self.grid_interval();
+        if interval == 0 || self.bpm <= 0.0 {
+            return stamp;
+        }
+        (stamp + interval / 2) / interval * interval
+    }
+}
@@ -0,0 +1,63 @@
+use super::input::MidiData;
+use super::output::MidiOutput;
+use super::MidiMessage;
+use bevy::prelude::*;
+
+/// [`Resource`](bevy::ecs::system::Resource) forwarding incoming midi data to
+/// [`MidiOutput`], with channels remapped.
+///
+/// While this resource and [`MidiOutput`] are present, every [`MidiData`] event
+/// on input channel `n` is sent on output channel `channels[n]`, or dropped if
+/// that is `None`. System messages have no channel and are forwarded as is.
+///
+/// The [`Default`] router forwards every channel unchanged.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidiRouter {
+    /// Output channel of each input channel, `0..16`.
+    pub channels: [Option<u8>; 16],
+}
+
+impl Default for MidiRouter {
+    fn default() -> Self {
+        let mut channels = [None; 16];
+        for (channel, output) in (0u8..).zip(&mut channels) {
+            *output = Some(channel);
+        }
+        MidiRouter { channels }
+    }
+}
+
+impl MidiRouter {
+    /// Send input `channel` to output channel `to`, or drop it if `None`.
+    pub fn route(&mut self, channel: u8, to: Option<u8>) {
+        self.channels[usize::from(channel & 0x0F)] = to.map(|c| c & 0x0F);
+    }
+
+    /// Get the message `message` is forwarded as, if any.
+    #[must_use]
+    pub fn remap(&self, message: MidiMessage) -> Option<MidiMessage> {
+        let [status, a, b] = message.msg;
+        if status >= 0xF0 {
+            return Some(message);
+        }
+        let channel = self.channels[usize::from(message.channel())]?;
+        Some(MidiMessage {
+            msg: [(status & 0b1111_0000) | (channel & 0x0F), a, b],
+        })
+    }
+}
+
+pub(crate) fn route_midi(
+    router: Res<MidiRouter>,
+    output: Option<Res<MidiOutput>>,
+    mut midi: EventReader<MidiData>,
+) {
+    let Some(output) = output else {
+        return;
+    };
+    for data in midi.read() {
+        if let Some(message) = router.remap(data.message) {
+            output.send(message);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use super::input::MidiData;
+use bevy::prelude::*;
+
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+const NULL_PARAMETER: [u8; 2] = [0x7F, 0x7F];
+
+/// An [`Event`](bevy::ecs::event::Event) for a Registered Parameter Number (RPN) change.
+///
+/// Fires from [`PreUpdate`] whenever a data entry controller (CC 6 or CC 38)
+/// is received after an RPN was selected with CC 101 and CC 100. Selecting the
+/// null parameter 127/127, as an RPN or an NRPN, deselects it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct RpnEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    /// 14-bit parameter number.
+    pub parameter: u16,
+    /// 14-bit value. The lower 7 bits are zero until CC 38 is received.
+    pub value: u16,
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for a Non-Registered Parameter Number (NRPN) change.
+///
+/// Fires from [`PreUpdate`] whenever a data entry controller (CC 6 or CC 38)
+/// is received after an NRPN was selected with CC 99 and CC 98. Selecting the
+/// null parameter 127/127, as an RPN or an NRPN, deselects it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct NrpnEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    /// 14-bit parameter number.
+    pub parameter: u16,
+    /// 14-bit value. The lower 7 bits are zero until CC 38 is received.
+    pub value: u16,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Selected {
+    #[default]
+    None,
+    Rpn,
+    Nrpn,
+}
+
+#[derive(Clone, Copy, Default)]
+struct ChannelState {
+    selected: Selected,
+    rpn: [u8; 2],
+    nrpn: [u8; 2],
+    value: [u8; 2],
+}
+
+#[derive(Default)]
+pub(crate) struct RpnDecoder {
+    channels: [ChannelState; 16],
+}
+
+pub(crate) fn decode_rpn(
+    mut decoder: Local<RpnDecoder>,
+    mut midi: EventReader<MidiData>,
+    mut rpn: EventWriter<RpnEvent>,
+    mut nrpn: EventWriter<NrpnEvent>,
+) {
+    for data in midi.read() {
+        if !data.message.is_control_change() {
+            continue;
+        }
+        let [_, cc, value] = data.message.msg;
+        let channel = data.message.channel();
+        let state = &mut decoder.channels[channel as usize];
+
+        match cc {
+            RPN_MSB => {
+                state.rpn[0] = value;
+                state.selected = Selected::Rpn;
+            }
+            RPN_LSB => {
+                state.rpn[1] = value;
+                state.selected = Selected::Rpn;
+            }
+            NRPN_MSB => {
+                state.nrpn[0] = value;
+                state.selected = Selected::Nrpn;
+            }
+            NRPN_LSB => {
+                state.nrpn[1] = value;
+                state.selected = Selected::Nrpn;
+            }
+            DATA_ENTRY_MSB | DATA_ENTRY_LSB => {
+                if cc == DATA_ENTRY_MSB {
+                    state.value = [value, 0];
+                } else {
+                    state.value[1] = value;
+                }
+                let value = combine(state.value);
+                match state.selected {
+                    Selected::Rpn => {
+                        rpn.send(RpnEvent {
+                            channel,
+                            parameter: combine(state.rpn),
+                            value,
+                        });
+                    }
+                    Selected::Nrpn => {
+                        nrpn.send(NrpnEvent {
+                            channel,
+                            parameter: combine(state.nrpn),
+                            value,
+                        });
+                    }
+                    Selected::None => {}
+                }
+            }
+            _ => {}
+        }
+        // Parameter 127/127 is the "null" parameter, which deselects either kind
+        if (matches!(cc, RPN_MSB | RPN_LSB) && state.rpn == NULL_PARAMETER)
+            || (matches!(cc, NRPN_MSB | NRPN_LSB) && state.nrpn == NULL_PARAMETER)
+        {
+            state.selected = Selected::None;
+        }
+    }
+}
+
+fn combine([msb, lsb]: [u8; 2]) -> u16 {
+    (u16::from(msb & 0x7F) << 7) | u16::from(lsb & 0x7F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MidiMessage;
+
+    // Run the decoder over control changes on channel 0
+    fn decode(ccs: &[(u8, u8)]) -> (Vec<RpnEvent>, Vec<NrpnEvent>) {
+        let mut app = App::new();
+        app.add_event::<MidiData>()
+            .add_event::<RpnEvent>()
+            .add_event::<NrpnEvent>()
+            .add_systems(Update, decode_rpn);
+        for &(cc, value) in ccs {
+            app.world_mut().send_event(MidiData {
+                stamp: 0,
+                message: MidiMessage::control_change(0, cc, value),
+            });
+        }
+        app.update();
+        let world = app.world_mut();
+        let rpn = world.resource_mut::<Events<RpnEvent>>().drain().collect();
+        let nrpn = world.resource_mut::<Events<NrpnEvent>>().drain().collect();
+        (rpn, nrpn)
+    }
+
+    #[test]
+    fn decodes_rpn_and_nrpn() {
+        let (rpn, nrpn) = decode(&[
+            (RPN_MSB, 0),
+            (RPN_LSB, 1),
+            (DATA_ENTRY_MSB, 64),
+            (DATA_ENTRY_LSB, 1),
+        ]);
+        assert_eq!(
+            rpn,
+            vec![
+                RpnEvent {
+                    channel: 0,
+                    parameter: 1,
+                    value: 64 << 7,
+                },
+                RpnEvent {
+                    channel: 0,
+                    parameter: 1,
+                    value: 64 << 7 | 1,
+                },
+            ]
+        );
+        assert!(nrpn.is_empty());
+
+        let (rpn, nrpn) = decode(&[(NRPN_MSB, 1), (NRPN_LSB, 2), (DATA_ENTRY_MSB, 3)]);
+        assert!(rpn.is_empty());
+        assert_eq!(
+            nrpn,
+            vec![NrpnEvent {
+                channel: 0,
+                parameter: 1 << 7 | 2,
+                value: 3 << 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn null_parameter_deselects() {
+        let (rpn, nrpn) = decode(&[
+            (RPN_MSB, 0),
+            (RPN_LSB, 0),
+            (RPN_MSB, 0x7F),
+            (RPN_LSB, 0x7F),
+            (DATA_ENTRY_MSB, 64),
+        ]);
+        assert!(rpn.is_empty());
+        assert!(nrpn.is_empty());
+
+        let (rpn, nrpn) = decode(&[
+            (NRPN_MSB, 1),
+            (NRPN_LSB, 2),
+            (NRPN_MSB, 0x7F),
+            (NRPN_LSB, 0x7F),
+            (DATA_ENTRY_MSB, 64),
+        ]);
+        assert!(rpn.is_empty());
+        assert!(nrpn.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use super::clock::{MidiClock, CLOCKS_PER_BEAT};
+use super::output::MidiOutput;
+use bevy::prelude::*;
+
+/// A note played by a [`MidiSequencer`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SequencerStep {
+    /// Position of the note in the sequence, in beats from its start.
+    pub beat: f32,
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    pub duration_beats: f32,
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) looping a sequence of notes on
+/// [`MidiOutput`], in time with the incoming [`MidiClock`].
+///
+/// While this resource is present, with [`MidiClockPlugin`](crate::clock::MidiClockPlugin)
+/// and [`MidiOutputPlugin`](crate::output::MidiOutputPlugin) added, the steps are
+/// played whenever the clock is running. Positions are rounded to the clock's
+/// resolution of [`CLOCKS_PER_BEAT`] ticks per beat; steps at or past
+/// `length_beats` are never played.
+///
+/// Steps can be changed at any time. Notes that are already playing end after
+/// their duration even if their step is removed.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiSequencer {
+    pub steps: Vec<SequencerStep>,
+    /// Length of the loop, in beats.
+    pub length_beats: f32,
+    // Next clock tick to play, while the clock is running
+    next_tick: Option<u64>,
+    // Channel, key and end tick of the notes playing
+    playing: Vec<(u8, u8, u64)>,
+}
+
+impl MidiSequencer {
+    /// Create an empty sequence looping every `length_beats` beats.
+    #[must_use]
+    pub fn new(length_beats: f32) -> Self {
+        MidiSequencer {
+            steps: Vec::new(),
+            length_beats,
+            next_tick: None,
+            playing: Vec::new(),
+        }
+    }
+
+    /// Add a step to the sequence.
+    pub fn add_step(&mut self, step: SequencerStep) {
+        self.steps.push(step);
+    }
+
+    /// Remove all steps starting at `beat`.
+    pub fn remove_steps_at(&mut self, beat: f32) {
+        self.steps.retain(|step| step.beat != beat);
+    }
+
+    fn release_all(&mut self, output: &MidiOutput) {
+        for (channel, key, _) in self.playing.drain(..) {
+            output.note_off(channel, key, 0);
+        }
+    }
+
+    fn play_tick(&mut self, tick: u64, output: &MidiOutput) {
+        let (ended, playing) = self.playing.drain(..).partition(|(_, _, end)| *end <= tick);
+        self.playing = playing;
+        for (channel, key, _) in ended {
+            output.note_off(channel, key, 0);
+        }
+
+        let length = to_ticks(self.length_beats);
+        if length == 0 {
+            return;
+        }
+        let position = tick % length;
+        for step in &self.steps {
+            if to_ticks(step.beat) != position {
+                continue;
+            }
+            // A re-struck note ends the previous one first
+            if let Some(i) = self
+                .playing
+                .iter()
+                .position(|(c, k, _)| *c == step.channel && *k == step.key)
+            {
+                self.playing.swap_remove(i);
+                output.note_off(step.channel, step.key, 0);
+            }
+            output.note_on(step.channel, step.key, step.velocity);
+            let end = tick + to_ticks(step.duration_beats).max(1);
+            self.playing.push((step.channel, step.key, end));
+        }
+    }
+}
+
+fn to_ticks(beats: f32) -> u64 {
+    (beats.max(0.0) * CLOCKS_PER_BEAT as f32).round() as u64
+}
+
+pub(crate) fn run_sequencer(
+    mut sequencer: ResMut<MidiSequencer>,
+    clock: Res<MidiClock>,
+    output: Option<Res<MidiOutput>>,
+) {
+    let Some(output) = output else {
+        return;
+    };
+    if !clock.is_running() {
+        if sequencer.next_tick.take().is_some() {
+            sequencer.release_all(&output);
+        }
+        return;
+    }
+    let ticks = clock.ticks();
+    let next = match sequencer.next_tick {
+        // Restarted from the beginning
+        Some(next) if next > ticks + 1 => {
+            sequencer.release_all(&output);
+            0
+        }
+        Some(next) => next,
+        None => ticks,
+    };
+    for tick in next..=ticks {
+        sequencer.play_tick(tick, &output);
+    }
+    sequencer.next_tick = Some(next.max(ticks + 1));
+}
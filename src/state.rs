@@ -0,0 +1,255 @@
+use super::input::MidiData;
+use bevy::prelude::*;
+use bevy_midi_core::{CHANNEL_PRESSURE_STATUS, POLY_AFTERTOUCH_STATUS};
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the last received value of
+/// every control change, per channel.
+///
+/// Updated from [`PreUpdate`]; all values start at `0`.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiCcState {
+    values: [[u8; 128]; 16],
+}
+
+impl Default for MidiCcState {
+    fn default() -> Self {
+        MidiCcState {
+            values: [[0; 128]; 16],
+        }
+    }
+}
+
+impl MidiCcState {
+    /// Get the last value of controller `cc` on `channel`.
+    #[must_use]
+    pub fn get(&self, channel: u8, cc: u8) -> u8 {
+        self.values[usize::from(channel & 0x0F)][usize::from(cc & 0x7F)]
+    }
+
+    /// Get the last values of all controllers on `channel`, indexed by controller number.
+    #[must_use]
+    pub fn channel(&self, channel: u8) -> &[u8; 128] {
+        &self.values[usize::from(channel & 0x0F)]
+    }
+}
+
+pub(crate) fn update_cc_state(mut state: ResMut<MidiCcState>, mut midi: EventReader<MidiData>) {
+    for data in midi.read() {
+        if data.message.is_control_change() {
+            let [_, cc, value] = data.message.msg;
+            state.values[usize::from(data.message.channel())][usize::from(cc & 0x7F)] = value;
+        }
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for the change of a controller's value
+/// in [`MidiCcState`] since the previous frame.
+///
+/// This event fires from [`PreUpdate`], once per frame for each controller whose
+/// value changed, even if several control changes were received for it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct MidiCcDeltaEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub cc: u8,
+    pub delta: i8,
+}
+
+pub(crate) fn emit_cc_deltas(
+    state: Res<MidiCcState>,
+    mut previous: Local<MidiCcState>,
+    mut events: EventWriter<MidiCcDeltaEvent>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for (channel, (values, previous)) in (0u8..).zip(state.values.iter().zip(&previous.values)) {
+        for (cc, (value, previous)) in (0u8..).zip(values.iter().zip(previous)) {
+            let delta = i16::from(*value) - i16::from(*previous);
+            if delta != 0 {
+                events.send(MidiCcDeltaEvent {
+                    channel,
+                    cc,
+                    // Both values are in 0..128, but don't panic on bad input
+                    delta: delta.clamp(-128, 127) as i8,
+                });
+            }
+        }
+    }
+    previous.values = state.values;
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the last received polyphonic
+/// aftertouch (key pressure) value of every key, per channel.
+///
+/// Updated from [`PreUpdate`]; all values start at `0`.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiAfterTouchState {
+    values: [[u8; 128]; 16],
+}
+
+impl Default for MidiAfterTouchState {
+    fn default() -> Self {
+        MidiAfterTouchState {
+            values: [[0; 128]; 16],
+        }
+    }
+}
+
+impl MidiAfterTouchState {
+    /// Get the last pressure of `key` on `channel`.
+    #[must_use]
+    pub fn get(&self, channel: u8, key: u8) -> u8 {
+        self.values[usize::from(channel & 0x0F)][usize::from(key & 0x7F)]
+    }
+
+    /// Get the last pressures of all keys on `channel`, indexed by key.
+    #[must_use]
+    pub fn channel(&self, channel: u8) -> &[u8; 128] {
+        &self.values[usize::from(channel & 0x0F)]
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for a polyphonic aftertouch (key pressure) message.
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct AfterTouchEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub key: u8,
+    pub pressure: u8,
+}
+
+pub(crate) fn update_aftertouch_state(
+    mut state: ResMut<MidiAfterTouchState>,
+    mut midi: EventReader<MidiData>,
+    mut events: EventWriter<AfterTouchEvent>,
+) {
+    for data in midi.read() {
+        if data.message.msg[0] & 0b1111_0000 == POLY_AFTERTOUCH_STATUS {
+            let channel = data.message.channel();
+            let [_, key, pressure] = data.message.msg;
+            state.values[usize::from(channel)][usize::from(key & 0x7F)] = pressure;
+            events.send(AfterTouchEvent {
+                channel,
+                key,
+                pressure,
+            });
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) holding the last received channel
+/// pressure (mono aftertouch) value, per channel.
+///
+/// Updated from [`PreUpdate`]; all values start at `0`.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct MidiChannelPressureState {
+    values: [u8; 16],
+}
+
+impl MidiChannelPressureState {
+    /// Get the last pressure on `channel`.
+    #[must_use]
+    pub fn get(&self, channel: u8) -> u8 {
+        self.values[usize::from(channel & 0x0F)]
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for a channel pressure (mono aftertouch) message.
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct ChannelPressureEvent {
+    /// Midi channel, `0..16`.
+    pub channel: u8,
+    pub pressure: u8,
+}
+
+pub(crate) fn update_channel_pressure_state(
+    mut state: ResMut<MidiChannelPressureState>,
+    mut midi: EventReader<MidiData>,
+    mut events: EventWriter<ChannelPressureEvent>,
+) {
+    for data in midi.read() {
+        if data.message.msg[0] & 0b1111_0000 == CHANNEL_PRESSURE_STATUS {
+            let channel = data.message.channel();
+            let pressure = data.message.msg[1];
+            state.values[usize::from(channel)] = pressure;
+            events.send(ChannelPressureEvent { channel, pressure });
+        }
+    }
+}
+
+/// [`Resource`](bevy::ecs::system::Resource) counting how many times each key has
+/// been pressed, per channel.
+///
+/// Updated from [`PreUpdate`]; NoteOn messages with a velocity of `0` are not counted.
+#[derive(Resource, Clone, Debug)]
+pub struct MidiNoteHistogram {
+    counts: [[u32; 128]; 16],
+    total: u64,
+}
+
+impl Default for MidiNoteHistogram {
+    fn default() -> Self {
+        MidiNoteHistogram {
+            counts: [[0; 128]; 16],
+            total: 0,
+        }
+    }
+}
+
+impl MidiNoteHistogram {
+    /// Get the number of times `key` was pressed on `channel`.
+    #[must_use]
+    pub fn get(&self, channel: u8, key: u8) -> u32 {
+        self.counts[usize::from(channel & 0x0F)][usize::from(key & 0x7F)]
+    }
+
+    /// Get the press counts of all keys on `channel`, indexed by key.
+    #[must_use]
+    pub fn channel(&self, channel: u8) -> &[u32; 128] {
+        &self.counts[usize::from(channel & 0x0F)]
+    }
+
+    /// Get the key pressed most often on `channel`, or `None` if no key was pressed.
+    /// Ties go to the lowest key.
+    #[must_use]
+    pub fn most_played_note(&self, channel: u8) -> Option<u8> {
+        let counts = self.channel(channel);
+        let (key, count) = counts
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, count)| **count)?;
+        (*count > 0).then_some(key as u8)
+    }
+
+    /// Get the number of key presses counted, on all channels.
+    #[must_use]
+    pub fn total_events(&self) -> u64 {
+        self.total
+    }
+
+    /// Reset all counts to `0`.
+    pub fn clear(&mut self) {
+        *self = MidiNoteHistogram::default();
+    }
+}
+
+pub(crate) fn update_note_histogram(
+    mut histogram: ResMut<MidiNoteHistogram>,
+    mut midi: EventReader<MidiData>,
+) {
+    for data in midi.read() {
+        let [_, key, velocity] = data.message.msg;
+        if data.message.is_note_on() && velocity > 0 {
+            let count =
+                &mut histogram.counts[usize::from(data.message.channel())][usize::from(key & 0x7F)];
+            *count = count.saturating_add(1);
+            histogram.total += 1;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::input::MidiData;
+use bevy::prelude::*;
+
+const SUSTAIN_PEDAL: u8 = 64;
+
+/// [`Resource`](bevy::ecs::system::Resource) that holds back NoteOffs while the
+/// sustain pedal (CC 64) is pressed.
+///
+/// While this resource is present, every [`MidiData`] event is re-emitted as
+/// [`SustainedMidiData`], except NoteOffs received while the pedal is down on
+/// their channel. Those are buffered and forwarded all at once when the pedal is
+/// released, or just before the same key is struck again.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SustainPedalTracker {
+    sustained: [bool; 16],
+    buffered: Vec<MidiData>,
+}
+
+impl SustainPedalTracker {
+    /// Check whether the sustain pedal is pressed on `channel`.
+    #[must_use]
+    pub fn is_sustained(&self, channel: u8) -> bool {
+        self.sustained[usize::from(channel & 0x0F)]
+    }
+
+    /// Get the NoteOffs currently held back by the pedal.
+    #[must_use]
+    pub fn buffered(&self) -> &[MidiData] {
+        &self.buffered
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for incoming midi data after sustain has
+/// been applied by the [`SustainPedalTracker`].
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Debug, Event)]
+pub struct SustainedMidiData(pub MidiData);
+
+fn is_release(data: &MidiData) -> bool {
+    data.message.is_note_off() || (data.message.is_note_on() && data.message.msg[2] == 0)
+}
+
+pub(crate) fn track_sustain(
+    mut tracker: ResMut<SustainPedalTracker>,
+    mut midi: EventReader<MidiData>,
+    mut sustained: EventWriter<SustainedMidiData>,
+) {
+    for data in midi.read() {
+        // System messages have no channel
+        if data.message.msg[0] >= 0xF0 {
+            sustained.send(SustainedMidiData(data.clone()));
+            continue;
+        }
+        let channel = data.message.channel();
+        let [_, key, value] = data.message.msg;
+
+        if data.message.is_control_change() && key == SUSTAIN_PEDAL {
+            let pressed = value >= 64;
+            tracker.sustained[usize::from(channel)] = pressed;
+            if !pressed {
+                let (released, kept) = tracker
+                    .buffered
+                    .drain(..)
+                    .partition(|d| d.message.channel() == channel);
+                tracker.buffered = kept;
+                sustained.send_batch(released.into_iter().map(SustainedMidiData));
+            }
+        } else if is_release(data) && tracker.is_sustained(channel) {
+            tracker.buffered.push(data.clone());
+            continue;
+        } else if data.message.is_note_on() {
+            // Re-striking a held key ends the sustained note first
+            if let Some(i) = tracker
+                .buffered
+                .iter()
+                .position(|d| d.message.channel() == channel && d.message.msg[1] == key)
+            {
+                let released = tracker.buffered.remove(i);
+                sustained.send(SustainedMidiData(released));
+            }
+        }
+        sustained.send(SustainedMidiData(data.clone()));
+    }
+}
@@ -0,0 +1,574 @@
+use bevy::prelude::*;
+
+pub use bevy_midi_core::SysExBuffer;
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const UNIVERSAL_NON_REALTIME: u8 = 0x7E;
+const UNIVERSAL_REALTIME: u8 = 0x7F;
+const MMC_COMMAND: u8 = 0x06;
+const GENERAL_INFORMATION: u8 = 0x06;
+const IDENTITY_REPLY: u8 = 0x02;
+const MIDI_TUNING: u8 = 0x08;
+
+/// The universal Identity Request, addressed to all devices.
+pub const IDENTITY_REQUEST: [u8; 6] = [
+    SYSEX_START,
+    UNIVERSAL_NON_REALTIME,
+    0x7F,
+    GENERAL_INFORMATION,
+    0x01,
+    SYSEX_END,
+];
+
+/// An [`Event`](bevy::ecs::event::Event) for an incoming System Exclusive message.
+///
+/// This event fires from [`PreUpdate`].
+#[derive(Clone, Debug, Event)]
+pub struct MidiSysExData {
+    pub stamp: u64,
+    /// The complete message, including the leading `0xF0` and trailing `0xF7`.
+    pub data: Vec<u8>,
+}
+
+impl MidiSysExData {
+    /// Parse this message as a MIDI Machine Control command, if it is one.
+    #[must_use]
+    pub fn try_as_mmc(&self) -> Option<MmcMessage> {
+        MmcMessage::parse(&self.data)
+    }
+
+    /// Parse this message as a reply to an Identity Request, if it is one.
+    #[must_use]
+    pub fn try_as_identity_response(&self) -> Option<DeviceIdentityResponse> {
+        DeviceIdentityResponse::parse(&self.data)
+    }
+
+    /// Parse this message as a MIDI Tuning Standard message, if it is one.
+    #[must_use]
+    pub fn try_as_mts(&self) -> Option<MtsMessage> {
+        MtsMessage::parse(&self.data)
+    }
+}
+
+/// A MIDI Machine Control (MMC) command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MmcMessage {
+    Stop,
+    Play,
+    FastForward,
+    Rewind,
+    Record,
+    Pause,
+    Reset,
+}
+
+impl MmcMessage {
+    /// Parse a complete SysEx message of the form `F0 7F <device> 06 <command> F7`.
+    ///
+    /// Deferred play is reported as [`MmcMessage::Play`], and record strobe as
+    /// [`MmcMessage::Record`]. Returns `None` for other messages and unsupported commands.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        match data {
+            [SYSEX_START, UNIVERSAL_REALTIME, _, MMC_COMMAND, command, SYSEX_END] => {
+                match command {
+                    0x01 => Some(MmcMessage::Stop),
+                    0x02 | 0x03 => Some(MmcMessage::Play),
+                    0x04 => Some(MmcMessage::FastForward),
+                    0x05 => Some(MmcMessage::Rewind),
+                    0x06 => Some(MmcMessage::Record),
+                    0x09 => Some(MmcMessage::Pause),
+                    0x0D => Some(MmcMessage::Reset),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An [`Event`](bevy::ecs::event::Event) for an incoming MIDI Machine Control command.
+///
+/// This event fires from [`PreUpdate`], alongside the [`MidiSysExData`] it was parsed from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Event)]
+pub struct MmcEvent {
+    pub stamp: u64,
+    /// The target device, where `0x7F` addresses all devices.
+    pub device_id: u8,
+    pub message: MmcMessage,
+}
+
+impl MmcEvent {
+    pub(crate) fn from_sysex(data: &MidiSysExData) -> Option<Self> {
+        data.try_as_mmc().map(|message| MmcEvent {
+            stamp: data.stamp,
+            device_id: data.data[2],
+            message,
+        })
+    }
+}
+
+/// A device's reply to an Identity Request, see
+/// [`MidiOutput::send_identity_request`](crate::output::MidiOutput::send_identity_request).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct DeviceIdentityResponse {
+    /// Manufacturer ID. One byte IDs are stored as is, three byte IDs (starting
+    /// with `0x00`) as a 21-bit value of their three 7-bit bytes.
+    pub manufacturer_id: u32,
+    /// 14-bit device family code.
+    pub family_code: u16,
+    /// 14-bit device family member code.
+    pub member_code: u16,
+    /// Software revision, in a manufacturer specific format.
+    pub revision: [u8; 4],
+}
+
+impl DeviceIdentityResponse {
+    /// Parse a complete SysEx message of the form
+    /// `F0 7E <device> 06 02 <manufacturer> <family> <member> <revision> F7`.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let [SYSEX_START, UNIVERSAL_NON_REALTIME, _, GENERAL_INFORMATION, IDENTITY_REPLY, rest @ .., SYSEX_END] =
+            data
+        else {
+            return None;
+        };
+        let (manufacturer_id, rest) = match rest {
+            [0x00, a, b, rest @ ..] => (u32::from(*a) << 7 | u32::from(*b), rest),
+            [id, rest @ ..] => (u32::from(*id), rest),
+            [] => return None,
+        };
+        let [family_lsb, family_msb, member_lsb, member_msb, r0, r1, r2, r3] = rest else {
+            return None;
+        };
+        Some(DeviceIdentityResponse {
+            manufacturer_id,
+            family_code: u16::from(*family_lsb) | u16::from(*family_msb) << 7,
+            member_code: u16::from(*member_lsb) | u16::from(*member_msb) << 7,
+            revision: [*r0, *r1, *r2, *r3],
+        })
+    }
+}
+
+/// The tuning of a single note in a [`MtsMessage`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct NoteTuning {
+    /// The equal tempered note at or below the target pitch, `0..128`.
+    pub semitone: u8,
+    /// 14-bit fraction of a semitone above `semitone`, in steps of 1/16384.
+    pub fraction: u16,
+}
+
+impl NoteTuning {
+    /// Get the tuned pitch, in fractional midi note numbers.
+    #[must_use]
+    pub fn pitch(&self) -> f32 {
+        f32::from(self.semitone) + f32::from(self.fraction) / 16384.0
+    }
+
+    // Parse the three byte `xx yy zz` form, where `7F 7F 7F` means no change
+    fn parse(data: &[u8]) -> Option<Option<Self>> {
+        match data {
+            [0x7F, 0x7F, 0x7F] => Some(None),
+            [semitone @ 0..=0x7F, msb @ 0..=0x7F, lsb @ 0..=0x7F] => Some(Some(NoteTuning {
+                semitone: *semitone,
+                fraction: u16::from(*msb) << 7 | u16::from(*lsb),
+            })),
+            _ => None,
+        }
+    }
+}
+
+/// A MIDI Tuning Standard (MTS) message, retuning individual notes.
+///
+/// Tunings of `None` leave the note's tuning unchanged.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MtsMessage {
+    /// A complete tuning program, for all 128 notes.
+    BulkTuningDump {
+        program: u8,
+        name: String,
+        tunings: Vec<Option<NoteTuning>>,
+    },
+    /// New tunings for some notes of a program, as `(key, tuning)` pairs.
+    SingleNoteTuningChange {
+        /// The bank of the program, if given.
+        bank: Option<u8>,
+        program: u8,
+        changes: Vec<(u8, Option<NoteTuning>)>,
+    },
+    /// Offsets applied to every octave, on some channels.
+    ScaleTuning {
+        /// Bit `n` is set if channel `n` is affected.
+        channels: u16,
+        /// Offset of each pitch class from equal temperament in cents, from C to B.
+        cents: [f32; 12],
+    },
+}
+
+impl MtsMessage {
+    /// Parse a complete SysEx message of the form `F0 7E|7F <device> 08 <format> .. F7`.
+    ///
+    /// Supports bulk tuning dumps (`01`), single note tuning changes with and
+    /// without a bank (`07` and `02`), and one and two byte scale/octave tunings
+    /// (`08` and `09`). The checksum of bulk dumps isn't verified, since devices
+    /// disagree on how it is computed.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let [SYSEX_START, UNIVERSAL_NON_REALTIME | UNIVERSAL_REALTIME, _, MIDI_TUNING, format, rest @ .., SYSEX_END] =
+            data
+        else {
+            return None;
+        };
+        match (format, rest) {
+            (0x01, [program, rest @ ..]) if rest.len() == 16 + 128 * 3 + 1 => {
+                let (name, rest) = rest.split_at(16);
+                let tunings = rest[..128 * 3]
+                    .chunks_exact(3)
+                    .map(NoteTuning::parse)
+                    .collect::<Option<_>>()?;
+                Some(MtsMessage::BulkTuningDump {
+                    program: *program,
+                    name: String::from_utf8_lossy(name).trim_end().to_string(),
+                    tunings,
+                })
+            }
+            (0x02, [program, count, rest @ ..]) => Some(MtsMessage::SingleNoteTuningChange {
+                bank: None,
+                program: *program,
+                changes: parse_note_changes(*count, rest)?,
+            }),
+            (0x07, [bank, program, count, rest @ ..]) => Some(MtsMessage::SingleNoteTuningChange {
+                bank: Some(*bank),
+                program: *program,
+                changes: parse_note_changes(*count, rest)?,
+            }),
+            (0x08, [ff, gg, hh, rest @ ..]) if rest.len() == 12 => {
+                let mut cents = [0.0; 12];
+                for (cents, offset) in cents.iter_mut().zip(rest) {
+                    // 0x40 is equal temperament, in steps of one cent
+                    *cents = f32::from(*offset) - 64.0;
+                }
+                Some(MtsMessage::ScaleTuning {
+                    channels: channel_mask(*ff, *gg, *hh),
+                    cents,
+                })
+            }
+            (0x09, [ff, gg, hh, rest @ ..]) if rest.len() == 24 => {
+                let mut cents = [0.0; 12];
+                for (cents, offset) in cents.iter_mut().zip(rest.chunks_exact(2)) {
+                    // 0x2000 is equal temperament, ranging over +/-100 cents
+                    let offset = u16::from(offset[0]) << 7 | u16::from(offset[1]);
+                    *cents = (f32::from(offset) - 8192.0) / 8192.0 * 100.0;
+                }
+                Some(MtsMessage::ScaleTuning {
+                    channels: channel_mask(*ff, *gg, *hh),
+                    cents,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Parse `count` of `kk xx yy zz` note tunings
+fn parse_note_changes(count: u8, data: &[u8]) -> Option<Vec<(u8, Option<NoteTuning>)>> {
+    if data.len() != usize::from(count) * 4 {
+        return None;
+    }
+    data.chunks_exact(4)
+        .map(|change| Some((change[0], NoteTuning::parse(&change[1..])?)))
+        .collect()
+}
+
+// `ff` holds channels 14 and 15, `gg` 7 to 13 and `hh` 0 to 6
+fn channel_mask(ff: u8, gg: u8, hh: u8) -> u16 {
+    u16::from(ff & 0x03) << 14 | u16::from(gg & 0x7F) << 7 | u16::from(hh & 0x7F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sysex(body: &[u8]) -> Vec<u8> {
+        let mut data = vec![SYSEX_START];
+        data.extend_from_slice(body);
+        data.push(SYSEX_END);
+        data
+    }
+
+    fn bulk_dump(tunings: usize) -> Vec<u8> {
+        let mut body = vec![UNIVERSAL_NON_REALTIME, 0x7F, MIDI_TUNING, 0x01, 5];
+        body.extend_from_slice(b"Just            ");
+        for key in 0..tunings {
+            body.extend_from_slice(&[key as u8, 0x20, 0x00]);
+        }
+        // Checksum
+        body.push(0x00);
+        sysex(&body)
+    }
+
+    #[test]
+    fn mts_bulk_tuning_dump() {
+        let Some(MtsMessage::BulkTuningDump {
+            program,
+            name,
+            tunings,
+        }) = MtsMessage::parse(&bulk_dump(128))
+        else {
+            panic!("not a bulk tuning dump");
+        };
+        assert_eq!(program, 5);
+        assert_eq!(name, "Just");
+        assert_eq!(tunings.len(), 128);
+        assert_eq!(
+            tunings[69],
+            Some(NoteTuning {
+                semitone: 69,
+                fraction: 0x1000,
+            })
+        );
+        assert_eq!(tunings[69].unwrap().pitch(), 69.25);
+    }
+
+    #[test]
+    fn mts_bulk_tuning_dump_needs_all_notes() {
+        assert_eq!(MtsMessage::parse(&bulk_dump(127)), None);
+        assert_eq!(MtsMessage::parse(&bulk_dump(129)), None);
+    }
+
+    #[test]
+    fn mts_single_note_tuning_change() {
+        let data = sysex(&[
+            UNIVERSAL_REALTIME,
+            0x7F,
+            MIDI_TUNING,
+            0x02,
+            3,
+            2,
+            60,
+            61,
+            0x7F,
+            0x7F,
+            64,
+            0x7F,
+            0x7F,
+            0x7F,
+        ]);
+        assert_eq!(
+            MtsMessage::parse(&data),
+            Some(MtsMessage::SingleNoteTuningChange {
+                bank: None,
+                program: 3,
+                changes: vec![
+                    (
+                        60,
+                        Some(NoteTuning {
+                            semitone: 61,
+                            fraction: 0x3FFF,
+                        })
+                    ),
+                    (64, None),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn mts_single_note_tuning_change_with_bank() {
+        let data = sysex(&[
+            UNIVERSAL_NON_REALTIME,
+            0x7F,
+            MIDI_TUNING,
+            0x07,
+            1,
+            3,
+            1,
+            60,
+            60,
+            0x40,
+            0x00,
+        ]);
+        assert_eq!(
+            MtsMessage::parse(&data),
+            Some(MtsMessage::SingleNoteTuningChange {
+                bank: Some(1),
+                program: 3,
+                changes: vec![(
+                    60,
+                    Some(NoteTuning {
+                        semitone: 60,
+                        fraction: 0x2000,
+                    })
+                )],
+            })
+        );
+        // One change announced, none given
+        let data = sysex(&[UNIVERSAL_NON_REALTIME, 0x7F, MIDI_TUNING, 0x07, 1, 3, 1]);
+        assert_eq!(MtsMessage::parse(&data), None);
+    }
+
+    #[test]
+    fn mts_one_byte_scale_tuning() {
+        let mut body = vec![
+            UNIVERSAL_NON_REALTIME,
+            0x7F,
+            MIDI_TUNING,
+            0x08,
+            0x03,
+            0x7F,
+            0x01,
+        ];
+        body.extend_from_slice(&[
+            0x00, 0x7F, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x4A,
+        ]);
+        let Some(MtsMessage::ScaleTuning { channels, cents }) = MtsMessage::parse(&sysex(&body))
+        else {
+            panic!("not a scale tuning");
+        };
+        assert_eq!(channels, 0xC000 | 0x3F80 | 0x0001);
+        assert_eq!(cents[0], -64.0);
+        assert_eq!(cents[1], 63.0);
+        assert_eq!(cents[2], 0.0);
+        assert_eq!(cents[11], 10.0);
+    }
+
+    #[test]
+    fn mts_two_byte_scale_tuning() {
+        let mut body = vec![
+            UNIVERSAL_NON_REALTIME,
+            0x7F,
+            MIDI_TUNING,
+            0x09,
+            0x00,
+            0x00,
+            0x02,
+        ];
+        body.extend_from_slice(&[0x00, 0x00, 0x7F, 0x7F]);
+        for _ in 2..12 {
+            body.extend_from_slice(&[0x40, 0x00]);
+        }
+        let Some(MtsMessage::ScaleTuning { channels, cents }) = MtsMessage::parse(&sysex(&body))
+        else {
+            panic!("not a scale tuning");
+        };
+        assert_eq!(channels, 0x0002);
+        assert_eq!(cents[0], -100.0);
+        assert!((cents[1] - 99.99).abs() < 0.01);
+        assert_eq!(cents[2], 0.0);
+        // One offset short
+        body.truncate(body.len() - 2);
+        assert_eq!(MtsMessage::parse(&sysex(&body)), None);
+    }
+
+    #[test]
+    fn mts_rejects_other_sysex() {
+        assert_eq!(MtsMessage::parse(&IDENTITY_REQUEST), None);
+        assert_eq!(
+            MtsMessage::parse(&sysex(&[0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F])),
+            None
+        );
+        assert_eq!(MtsMessage::parse(&[]), None);
+    }
+
+    fn identity_reply(manufacturer: &[u8]) -> Vec<u8> {
+        let mut body = vec![
+            UNIVERSAL_NON_REALTIME,
+            0x10,
+            GENERAL_INFORMATION,
+            IDENTITY_REPLY,
+        ];
+        body.extend_from_slice(manufacturer);
+        // Family and member codes, then the revision
+        body.extend_from_slice(&[0x01, 0x02, 0x7F, 0x00, 1, 2, 3, 4]);
+        sysex(&body)
+    }
+
+    #[test]
+    fn identity_with_one_byte_manufacturer() {
+        assert_eq!(
+            DeviceIdentityResponse::parse(&identity_reply(&[0x41])),
+            Some(DeviceIdentityResponse {
+                manufacturer_id: 0x41,
+                family_code: 0x0101,
+                member_code: 0x007F,
+                revision: [1, 2, 3, 4],
+            })
+        );
+    }
+
+    #[test]
+    fn identity_with_three_byte_manufacturer() {
+        let identity = DeviceIdentityResponse::parse(&identity_reply(&[0x00, 0x20, 0x29])).unwrap();
+        assert_eq!(identity.manufacturer_id, 0x20 << 7 | 0x29);
+        assert_eq!(identity.family_code, 0x0101);
+        assert_eq!(identity.member_code, 0x007F);
+        assert_eq!(identity.revision, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn identity_codes_are_lsb_first() {
+        let data = sysex(&[
+            UNIVERSAL_NON_REALTIME,
+            0x10,
+            GENERAL_INFORMATION,
+            IDENTITY_REPLY,
+            0x41,
+            0x7F,
+            0x01,
+            0x00,
+            0x7F,
+            0,
+            0,
+            0,
+            0,
+        ]);
+        let identity = DeviceIdentityResponse::parse(&data).unwrap();
+        assert_eq!(identity.family_code, 0x00FF);
+        assert_eq!(identity.member_code, 0x3F80);
+    }
+
+    #[test]
+    fn identity_rejects_other_messages() {
+        let mut truncated = identity_reply(&[0x41]);
+        truncated.remove(truncated.len() - 2);
+        assert_eq!(DeviceIdentityResponse::parse(&truncated), None);
+        assert_eq!(DeviceIdentityResponse::parse(&identity_reply(&[])), None);
+        assert_eq!(DeviceIdentityResponse::parse(&IDENTITY_REQUEST), None);
+        let mut mts = identity_reply(&[0x41]);
+        mts[3] = MIDI_TUNING;
+        assert_eq!(DeviceIdentityResponse::parse(&mts), None);
+    }
+
+    #[test]
+    fn mmc_commands() {
+        let commands = [
+            (0x01, MmcMessage::Stop),
+            (0x02, MmcMessage::Play),
+            (0x03, MmcMessage::Play),
+            (0x04, MmcMessage::FastForward),
+            (0x05, MmcMessage::Rewind),
+            (0x06, MmcMessage::Record),
+            (0x09, MmcMessage::Pause),
+            (0x0D, MmcMessage::Reset),
+        ];
+        for (command, message) in commands {
+            let data = sysex(&[UNIVERSAL_REALTIME, 0x7F, MMC_COMMAND, command]);
+            assert_eq!(MmcMessage::parse(&data), Some(message), "{command:#04x}");
+        }
+    }
+
+    #[test]
+    fn mmc_rejects_other_messages() {
+        // Locate, which isn't supported
+        let data = sysex(&[UNIVERSAL_REALTIME, 0x7F, MMC_COMMAND, 0x44]);
+        assert_eq!(MmcMessage::parse(&data), None);
+        // MMC response instead of command
+        let data = sysex(&[UNIVERSAL_REALTIME, 0x7F, 0x07, 0x01]);
+        assert_eq!(MmcMessage::parse(&data), None);
+        let data = sysex(&[UNIVERSAL_NON_REALTIME, 0x7F, MMC_COMMAND, 0x01]);
+        assert_eq!(MmcMessage::parse(&data), None);
+        assert_eq!(MmcMessage::parse(&IDENTITY_REQUEST), None);
+    }
+}
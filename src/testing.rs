@@ -0,0 +1,87 @@
+use super::input::{Message, Reply};
+use super::prelude::*;
+use bevy::prelude::*;
+use bevy_midi_core::{CONTROL_CHANGE_STATUS, NOTE_OFF_STATUS, NOTE_ON_STATUS};
+use crossbeam_channel::{Receiver, Sender};
+use std::time::Instant;
+
+/// [`Resource`](bevy::ecs::system::Resource) feeding [`MidiInput`] from test code
+/// instead of a real midi device.
+///
+/// Messages pushed with [`FakeMidiInput::inject`] are emitted as [`MidiData`]
+/// events on the next update, exactly as if they had been received from a device.
+#[derive(Resource, Clone)]
+pub struct FakeMidiInput {
+    sender: Sender<Reply>,
+    realtime_sender: Sender<Reply>,
+    // Keeps requests like `MidiInput::connect` from failing
+    _requests: Receiver<Message>,
+}
+
+impl FakeMidiInput {
+    /// Replace the [`MidiInput`] of `app` with one fed by a [`FakeMidiInput`],
+    /// which is inserted as a resource.
+    ///
+    /// [`MidiInputPlugin`] must already be added. Call this before the first
+    /// update, so that no background task is started for a real device.
+    pub fn install(app: &mut App) {
+        assert!(
+            app.is_plugin_added::<MidiInputPlugin>(),
+            "MidiInputPlugin must be added before installing FakeMidiInput"
+        );
+        let (m_sender, m_receiver) = crossbeam_channel::unbounded();
+        let (r_sender, r_receiver) = crossbeam_channel::unbounded();
+        let (realtime_sender, realtime_receiver) = crossbeam_channel::unbounded();
+
+        app.insert_resource(MidiInput::new(m_sender, r_receiver, realtime_receiver))
+            .insert_resource(FakeMidiInput {
+                sender: r_sender,
+                realtime_sender,
+                _requests: m_receiver,
+            });
+    }
+
+    /// Push `message` as if it had been received from a device.
+    pub fn inject(&self, stamp: u64, message: MidiMessage) {
+        // Realtime messages skip ahead, like those from a device
+        let sender = if message.msg[0] >= 0xF8 {
+            &self.realtime_sender
+        } else {
+            &self.sender
+        };
+        sender
+            .send(Reply::Midi(MidiData { stamp, message }, Instant::now()))
+            .expect("Couldn't inject midi message");
+    }
+}
+
+/// Inject a NoteOn message and run one update of `app`.
+///
+/// Installs a [`FakeMidiInput`] if there isn't one yet.
+pub fn inject_note_on(app: &mut App, channel: u8, key: u8, velocity: u8) {
+    inject(app, [NOTE_ON_STATUS | (channel & 0x0F), key, velocity]);
+}
+
+/// Inject a NoteOff message and run one update of `app`.
+///
+/// Installs a [`FakeMidiInput`] if there isn't one yet.
+pub fn inject_note_off(app: &mut App, channel: u8, key: u8, velocity: u8) {
+    inject(app, [NOTE_OFF_STATUS | (channel & 0x0F), key, velocity]);
+}
+
+/// Inject a control change message and run one update of `app`.
+///
+/// Installs a [`FakeMidiInput`] if there isn't one yet.
+pub fn inject_cc(app: &mut App, channel: u8, cc: u8, value: u8) {
+    inject(app, [CONTROL_CHANGE_STATUS | (channel & 0x0F), cc, value]);
+}
+
+fn inject(app: &mut App, msg: [u8; 3]) {
+    if !app.world().contains_resource::<FakeMidiInput>() {
+        FakeMidiInput::install(app);
+    }
+    app.world()
+        .resource::<FakeMidiInput>()
+        .inject(0, msg.into());
+    app.update();
+}
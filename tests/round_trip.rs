@@ -0,0 +1,107 @@
+//! Measures the round-trip latency of [`MidiInput`] through a virtual midi port.
+//!
+//! Needs a working midi backend, so it only runs with
+//! `cargo test --features round_trip_test --test round_trip`. The test fails if
+//! the 99th percentile latency exceeds `BEVY_MIDI_ROUND_TRIP_THRESHOLD_MS`
+//! milliseconds, 10 by default.
+#![cfg(all(test, feature = "round_trip_test", unix))]
+
+use bevy::prelude::*;
+use bevy_midi::prelude::*;
+use midir::os::unix::VirtualOutput;
+use std::time::{Duration, Instant};
+
+const VIRTUAL_PORT: &str = "bevy_midi round trip";
+const MESSAGES: usize = 100;
+const DEFAULT_THRESHOLD_MS: f64 = 10.0;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn threshold() -> Duration {
+    let ms = match std::env::var("BEVY_MIDI_ROUND_TRIP_THRESHOLD_MS") {
+        Ok(ms) => ms
+            .parse()
+            .expect("BEVY_MIDI_ROUND_TRIP_THRESHOLD_MS must be a number of milliseconds"),
+        Err(_) => DEFAULT_THRESHOLD_MS,
+    };
+    Duration::from_secs_f64(ms / 1000.0)
+}
+
+fn connect(app: &mut App) {
+    let start = Instant::now();
+    let mut connecting = false;
+    while !app.world().resource::<MidiInputConnection>().is_connected() {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "Timed out connecting to the virtual port"
+        );
+        app.update();
+        let input = app.world().resource::<MidiInput>();
+        if connecting {
+            continue;
+        }
+        if let Some((_, port)) = input.ports().iter().find(|(n, _)| n.contains(VIRTUAL_PORT)) {
+            input.connect(port.clone());
+            connecting = true;
+        }
+    }
+}
+
+// Update the app until a NoteOn for `key` is received
+fn wait_for_note_on(app: &mut App, key: u8) {
+    let start = Instant::now();
+    loop {
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "Timed out waiting for NoteOn {}",
+            key
+        );
+        app.update();
+        let mut events = app.world_mut().resource_mut::<Events<MidiData>>();
+        if events
+            .drain()
+            .any(|data| data.message.is_note_on() && data.message.msg[1] == key)
+        {
+            return;
+        }
+    }
+}
+
+#[test]
+fn round_trip_latency() {
+    let mut output = midir::MidiOutput::new("round trip")
+        .expect("Couldn't create midi output")
+        .create_virtual(VIRTUAL_PORT)
+        .expect("Couldn't create virtual output port");
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, MidiInputPlugin));
+    connect(&mut app);
+
+    let mut latencies = Vec::with_capacity(MESSAGES);
+    for i in 0..MESSAGES {
+        let key = (i % 128) as u8;
+        let sent = Instant::now();
+        output
+            .send(&[0b1001_0000, key, 100])
+            .expect("Couldn't send NoteOn");
+        wait_for_note_on(&mut app, key);
+        latencies.push(sent.elapsed());
+        output
+            .send(&[0b1000_0000, key, 0])
+            .expect("Couldn't send NoteOff");
+    }
+
+    latencies.sort();
+    let median = latencies[MESSAGES / 2];
+    let p99 = latencies[(MESSAGES * 99).div_ceil(100) - 1];
+    println!(
+        "Round trip latency: median {:?}, 99th percentile {:?}",
+        median, p99
+    );
+    assert!(
+        p99 <= threshold(),
+        "99th percentile latency {:?} exceeds {:?}",
+        p99,
+        threshold()
+    );
+}